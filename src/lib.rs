@@ -5,26 +5,196 @@ use ress::{Comment, CommentKind};
 use std::io::{Error as IoError, Write};
 
 mod rewrite;
+pub mod source_map;
 pub mod write_str;
 
+pub use source_map::SourceMap;
+pub use write_str::WriteString;
+
 /// The writer that will take in
 /// RESSA AST and write to the provided
 /// `impl Write` provided
 pub struct Writer<T: Write> {
     current_indent: usize,
-    at_top_level: bool,
-    in_for_init: bool,
+    bytes_written: usize,
+    current_line: usize,
+    current_column: usize,
+    source_map: Option<SourceMap>,
+    context: Vec<WriterContext>,
     new_line: String,
     indent: String,
     quote: Option<char>,
+    trailing_newline_per_part: bool,
+    decorators: bool,
+    paren_default_export: bool,
+    empty_class_body_compact: bool,
+    arrow_expression_body: bool,
+    generator_star_spacing: GeneratorStarSpacing,
+    explicit_nested_ternary: bool,
+    typescript_mode: bool,
+    multi_line_object_threshold: usize,
+    multi_line_list_threshold: usize,
+    minify: bool,
+    semicolons: SemicolonPolicy,
+    trailing_commas: TrailingCommaPolicy,
+    brace_style: BraceStyle,
+    hashbang: Option<String>,
     out: T,
 }
+/// The part of a program the `Writer` is currently in,
+/// these are kept as a stack so the enclosing
+/// contexts can also be inspected
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WriterContext {
+    /// Directly inside of a `Program`
+    TopLevel,
+    /// Inside of the braces of a function or method
+    FunctionBody,
+    /// Inside of the braces of a class
+    ClassBody,
+    /// Inside of a block statement
+    BlockStatement,
+    /// The first part of a c-style for loop's parenthetical
+    ForInit,
+}
+/// A minimal TypeScript interface, written with `Writer::write_interface`
+#[derive(Debug, Clone, Default)]
+pub struct Interface {
+    pub name: String,
+    pub members: Vec<InterfaceMember>,
+}
+/// A single member of an `Interface`, the type
+/// annotation is written as is
+/// ```ts
+/// bar(): void;
+/// baz: string;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct InterfaceMember {
+    pub name: String,
+    pub type_annotation: String,
+}
+/// Options to temporarily apply to a `Writer` with
+/// `Writer::with_options`, any option left as `None`
+/// will keep the writer's current value
+#[derive(Debug, Clone, Default)]
+pub struct WriterOptions {
+    /// The string to use as indentation
+    pub indent: Option<String>,
+    /// The string to separate new lines
+    pub new_line: Option<String>,
+    /// The character to wrap strings in
+    pub quote: Option<char>,
+}
+/// Where the opening brace of a block, function or class body is written,
+/// object literals and patterns always keep their brace on the same line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BraceStyle {
+    /// `if (a) {`
+    SameLine,
+    /// The brace starts a new line at the current indentation (Allman style)
+    NextLine,
+}
+/// When a trailing comma should be written after the last item of a
+/// list, only lists that are written across multiple lines are affected.
+/// Those are object literals over the `multi_line_object_threshold` and
+/// the other lists over the `multi_line_list_threshold`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrailingCommaPolicy {
+    /// Never write a trailing comma
+    Never,
+    /// Write a trailing comma where ES5 allows it, after the last item of
+    /// an array literal, object literal or import/export specifier list
+    Es5,
+    /// Write a trailing comma wherever it is allowed, this includes
+    /// function parameters and arguments. No comma is written after
+    /// a rest parameter since that would be a syntax error
+    All,
+}
+/// When the semicolons ending a statement should be written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SemicolonPolicy {
+    /// End every statement with a semicolon
+    Always,
+    /// Only write the semicolons that are required, the
+    /// end of a statement is left to automatic semicolon insertion.
+    /// Without a `new_line` (minified output) this acts like `Always`
+    Never,
+}
+/// Where the `*` of a generator function should be written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeneratorStarSpacing {
+    /// `function* foo() {}`
+    AfterFunction,
+    /// `function *foo() {}`
+    BeforeName,
+}
+/// The decorators to write along with a `Class`
+///
+/// RESSA's AST doesn't include decorators, so they
+/// are provided separately when calling `Writer::write_decorated_class`
+#[derive(Debug, Clone, Default)]
+pub struct ClassDecorators {
+    /// Decorators for the class itself
+    pub class: Vec<Expr>,
+    /// Decorators for each class member, matched to the class
+    /// body by index
+    pub members: Vec<Vec<Expr>>,
+}
+/// Presets for the `Builder`'s formatting options,
+/// only the options this crate supports are applied
+/// for each style: indentation, new lines, quotes,
+/// semicolons and trailing commas. Other parts of these
+/// styles, like parentheses around a single arrow function
+/// parameter or the spacing inside of braces, are not applied
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CodeStyle {
+    /// 4 space indent, original quotes
+    Default,
+    /// 2 space indent, double quotes, ES5 trailing commas
+    Prettier,
+    /// 2 space indent, single quotes, no semicolons
+    StandardJS,
+    /// No indentation or new lines, original quotes
+    Minified,
+    /// 2 space indent, single quotes, trailing commas
+    /// everywhere they are allowed
+    AirBnB,
+}
 /// For building a writer when not
 /// using the default
+///
+/// Each option can either be set by value while chaining
+/// (`Builder::new().indent("  ").build(dest)`) or in place
+/// using the `set_` version of the method
+///
+/// `build` consumes the builder, clone it first
+/// to create more than one writer with the same options
+/// ```
+/// use resw::Writer;
+/// let builder = Writer::builder().indent("  ");
+/// let first = builder.clone().build(Vec::new());
+/// let second = builder.quote('"').build(Vec::new());
+/// ```
 pub struct Builder<T: Write> {
     new_line: String,
     quote: Option<char>,
     indent: String,
+    trailing_newline_per_part: bool,
+    decorators: bool,
+    paren_default_export: bool,
+    empty_class_body_compact: bool,
+    arrow_expression_body: bool,
+    generator_star_spacing: GeneratorStarSpacing,
+    explicit_nested_ternary: bool,
+    typescript_mode: bool,
+    multi_line_object_threshold: usize,
+    multi_line_list_threshold: usize,
+    minify: bool,
+    semicolons: SemicolonPolicy,
+    trailing_commas: TrailingCommaPolicy,
+    brace_style: BraceStyle,
+    hashbang: Option<String>,
     p: ::std::marker::PhantomData<T>,
 }
 
@@ -34,27 +204,43 @@ impl<T: Write> Builder<T> {
             new_line: "\n".to_string(),
             quote: None,
             indent: " ".repeat(4),
+            trailing_newline_per_part: true,
+            decorators: false,
+            paren_default_export: false,
+            empty_class_body_compact: false,
+            arrow_expression_body: false,
+            generator_star_spacing: GeneratorStarSpacing::AfterFunction,
+            explicit_nested_ternary: false,
+            typescript_mode: false,
+            multi_line_object_threshold: usize::MAX,
+            multi_line_list_threshold: usize::MAX,
+            minify: false,
+            semicolons: SemicolonPolicy::Always,
+            trailing_commas: TrailingCommaPolicy::Never,
+            brace_style: BraceStyle::SameLine,
+            hashbang: None,
             p: ::std::marker::PhantomData,
         }
     }
     /// Set the string to separate new lines
     ///
     /// defaults to `"\n"`
-    pub fn new_line(&mut self, new_line: &str) -> &mut Self {
+    pub fn new_line(mut self, new_line: &str) -> Self {
         self.set_new_line(new_line);
         self
     }
     /// Set the string to separate new lines
     ///
     /// defaults to `"\n"`
-    pub fn set_new_line(&mut self, new_line: &str) {
+    pub fn set_new_line(&mut self, new_line: &str) -> &mut Self {
         self.new_line = new_line.to_string();
+        self
     }
     /// Sets the character to use to wrap strings in
     /// the default is to just use the quotes provided
     /// by the original text. Providing a quote character
     /// here will result in the Writer re-writing the string.
-    pub fn quote(&mut self, quote: char) -> &mut Self {
+    pub fn quote(mut self, quote: char) -> Self {
         self.set_quote(quote);
         self
     }
@@ -62,28 +248,315 @@ impl<T: Write> Builder<T> {
     /// the default is to just use the quotes provided
     /// by the original text. Providing a quote character
     /// here will result in the Writer re-writing the string.
-    pub fn set_quote(&mut self, quote: char) {
+    pub fn set_quote(&mut self, quote: char) -> &mut Self {
         self.quote = Some(quote);
+        self
     }
     /// Sets the string to use as indentation. By default this
     /// will be set to 4 spaces.
-    pub fn indent(&mut self, indent: &str) -> &mut Self {
+    pub fn indent(mut self, indent: &str) -> Self {
         self.set_indent(indent);
         self
     }
     /// Sets the string to use as indentation. By default this
     /// will be set to 4 spaces.
-    pub fn set_indent(&mut self, indent: &str) {
+    pub fn set_indent(&mut self, indent: &str) -> &mut Self {
         self.indent = indent.to_string();
+        self
     }
-    /// Finalize the writer with the destination.
-    pub fn build(&self, destination: T) -> Writer<T> {
-        Writer::create(
-            destination,
-            self.new_line.clone(),
-            self.quote.clone(),
-            self.indent.clone(),
-        )
+    /// Sets if `write_part` should end each part with a new line.
+    /// By default this will be set to true.
+    pub fn trailing_newline_per_part(mut self, value: bool) -> Self {
+        self.set_trailing_newline_per_part(value);
+        self
+    }
+    /// Sets if `write_part` should end each part with a new line.
+    /// By default this will be set to true.
+    pub fn set_trailing_newline_per_part(&mut self, value: bool) -> &mut Self {
+        self.trailing_newline_per_part = value;
+        self
+    }
+    /// Sets if decorators passed to `write_decorated_class` should
+    /// be written. By default this will be set to false.
+    pub fn decorators(mut self, value: bool) -> Self {
+        self.set_decorators(value);
+        self
+    }
+    /// Sets if decorators passed to `write_decorated_class` should
+    /// be written. By default this will be set to false.
+    pub fn set_decorators(&mut self, value: bool) -> &mut Self {
+        self.decorators = value;
+        self
+    }
+    /// Sets if the expression of an `export default` should be wrapped
+    /// in parentheses, functions and classes will never be wrapped.
+    /// By default this will be set to false.
+    pub fn paren_default_export(mut self, value: bool) -> Self {
+        self.set_paren_default_export(value);
+        self
+    }
+    /// Sets if the expression of an `export default` should be wrapped
+    /// in parentheses, functions and classes will never be wrapped.
+    /// By default this will be set to false.
+    pub fn set_paren_default_export(&mut self, value: bool) -> &mut Self {
+        self.paren_default_export = value;
+        self
+    }
+    /// Sets if a class with an empty body should be written
+    /// as `class A { }` on a single line. By default this will
+    /// be set to false.
+    pub fn empty_class_body_compact(mut self, value: bool) -> Self {
+        self.set_empty_class_body_compact(value);
+        self
+    }
+    /// Sets if a class with an empty body should be written
+    /// as `class A { }` on a single line. By default this will
+    /// be set to false.
+    pub fn set_empty_class_body_compact(&mut self, value: bool) -> &mut Self {
+        self.empty_class_body_compact = value;
+        self
+    }
+    /// Apply the formatting options of a `CodeStyle`, any option
+    /// can still be overridden after this is called
+    pub fn code_style(mut self, style: CodeStyle) -> Self {
+        self.set_code_style(style);
+        self
+    }
+    /// Apply the formatting options of a `CodeStyle`, any option
+    /// can still be overridden after this is called
+    pub fn set_code_style(&mut self, style: CodeStyle) -> &mut Self {
+        let (indent, new_line, quote) = match style {
+            CodeStyle::Default => ("    ", "\n", None),
+            CodeStyle::Prettier => ("  ", "\n", Some('"')),
+            CodeStyle::StandardJS | CodeStyle::AirBnB => ("  ", "\n", Some('\'')),
+            CodeStyle::Minified => ("", "", None),
+        };
+        self.indent = indent.to_string();
+        self.new_line = new_line.to_string();
+        self.quote = quote;
+        self.trailing_newline_per_part = style != CodeStyle::Minified;
+        self.minify = style == CodeStyle::Minified;
+        self.semicolons = match style {
+            CodeStyle::StandardJS => SemicolonPolicy::Never,
+            _ => SemicolonPolicy::Always,
+        };
+        self.trailing_commas = match style {
+            CodeStyle::Prettier => TrailingCommaPolicy::Es5,
+            CodeStyle::AirBnB => TrailingCommaPolicy::All,
+            _ => TrailingCommaPolicy::Never,
+        };
+        self
+    }
+    /// Sets if an arrow function whose body is a single `return`
+    /// should be written with an expression body (`x => x + 1`).
+    /// By default this will be set to false.
+    pub fn arrow_expression_body(mut self, value: bool) -> Self {
+        self.set_arrow_expression_body(value);
+        self
+    }
+    /// Sets if an arrow function whose body is a single `return`
+    /// should be written with an expression body (`x => x + 1`).
+    /// By default this will be set to false.
+    pub fn set_arrow_expression_body(&mut self, value: bool) -> &mut Self {
+        self.arrow_expression_body = value;
+        self
+    }
+    /// Sets where the `*` of a generator function is written, by default
+    /// this will be `GeneratorStarSpacing::AfterFunction`
+    pub fn generator_star_spacing(mut self, value: GeneratorStarSpacing) -> Self {
+        self.set_generator_star_spacing(value);
+        self
+    }
+    /// Sets where the `*` of a generator function is written, by default
+    /// this will be `GeneratorStarSpacing::AfterFunction`
+    pub fn set_generator_star_spacing(&mut self, value: GeneratorStarSpacing) -> &mut Self {
+        self.generator_star_spacing = value;
+        self
+    }
+    /// Sets if a conditional expression in the alternate position of
+    /// another conditional should be wrapped in parentheses
+    /// (`a ? b : (c ? d : e)`). By default this will be set to false.
+    pub fn explicit_nested_ternary(mut self, value: bool) -> Self {
+        self.set_explicit_nested_ternary(value);
+        self
+    }
+    /// Sets if a conditional expression in the alternate position of
+    /// another conditional should be wrapped in parentheses
+    /// (`a ? b : (c ? d : e)`). By default this will be set to false.
+    pub fn set_explicit_nested_ternary(&mut self, value: bool) -> &mut Self {
+        self.explicit_nested_ternary = value;
+        self
+    }
+    /// Sets if TypeScript only constructs like interfaces should be
+    /// written. By default this will be set to false.
+    pub fn typescript_mode(mut self, value: bool) -> Self {
+        self.set_typescript_mode(value);
+        self
+    }
+    /// Sets if TypeScript only constructs like interfaces should be
+    /// written. By default this will be set to false.
+    pub fn set_typescript_mode(&mut self, value: bool) -> &mut Self {
+        self.typescript_mode = value;
+        self
+    }
+    /// Sets the number of properties an object literal can have
+    /// before each property is written on its own line.
+    /// By default this will be set to `usize::MAX`
+    pub fn multi_line_object_threshold(mut self, threshold: usize) -> Self {
+        self.set_multi_line_object_threshold(threshold);
+        self
+    }
+    /// Sets the number of properties an object literal can have
+    /// before each property is written on its own line.
+    /// By default this will be set to `usize::MAX`
+    pub fn set_multi_line_object_threshold(&mut self, threshold: usize) -> &mut Self {
+        self.multi_line_object_threshold = threshold;
+        self
+    }
+    /// Sets the number of items an array literal, argument list,
+    /// parameter list or import/export specifier list can have
+    /// before each item is written on its own line.
+    /// By default this will be set to `usize::MAX`
+    pub fn multi_line_list_threshold(mut self, threshold: usize) -> Self {
+        self.set_multi_line_list_threshold(threshold);
+        self
+    }
+    /// Sets the number of items an array literal, argument list,
+    /// parameter list or import/export specifier list can have
+    /// before each item is written on its own line.
+    /// By default this will be set to `usize::MAX`
+    pub fn set_multi_line_list_threshold(&mut self, threshold: usize) -> &mut Self {
+        self.multi_line_list_threshold = threshold;
+        self
+    }
+    /// Sets if the output should be minified, this removes all
+    /// indentation and new lines and any spaces around binary, logical,
+    /// assignment, conditional and arrow operators that aren't needed.
+    /// By default this will be set to false.
+    pub fn minify(mut self, value: bool) -> Self {
+        self.set_minify(value);
+        self
+    }
+    /// Sets if the output should be minified, this removes all
+    /// indentation and new lines and any spaces around binary, logical,
+    /// assignment, conditional and arrow operators that aren't needed.
+    /// By default this will be set to false.
+    pub fn set_minify(&mut self, value: bool) -> &mut Self {
+        self.minify = value;
+        if value {
+            self.indent = String::new();
+            self.new_line = String::new();
+            self.trailing_newline_per_part = false;
+        }
+        self
+    }
+    /// Sets when optional semicolons should be written, by default
+    /// this will be `SemicolonPolicy::Always`.
+    /// `SemicolonPolicy::Never` relies on new lines between statements
+    pub fn semicolons(mut self, value: SemicolonPolicy) -> Self {
+        self.set_semicolons(value);
+        self
+    }
+    /// Sets when optional semicolons should be written, by default
+    /// this will be `SemicolonPolicy::Always`.
+    /// `SemicolonPolicy::Never` relies on new lines between statements
+    pub fn set_semicolons(&mut self, value: SemicolonPolicy) -> &mut Self {
+        self.semicolons = value;
+        self
+    }
+    /// Sets when a trailing comma should follow the last item of a list
+    /// that is written across multiple lines, by default this will be
+    /// `TrailingCommaPolicy::Never`
+    pub fn trailing_commas(mut self, value: TrailingCommaPolicy) -> Self {
+        self.set_trailing_commas(value);
+        self
+    }
+    /// Sets when a trailing comma should follow the last item of a list
+    /// that is written across multiple lines, by default this will be
+    /// `TrailingCommaPolicy::Never`
+    pub fn set_trailing_commas(&mut self, value: TrailingCommaPolicy) -> &mut Self {
+        self.trailing_commas = value;
+        self
+    }
+    /// Sets where the opening brace of a block is written, by default
+    /// this will be `BraceStyle::SameLine`
+    pub fn brace_style(mut self, value: BraceStyle) -> Self {
+        self.set_brace_style(value);
+        self
+    }
+    /// Sets where the opening brace of a block is written, by default
+    /// this will be `BraceStyle::SameLine`
+    pub fn set_brace_style(&mut self, value: BraceStyle) -> &mut Self {
+        self.brace_style = value;
+        self
+    }
+    /// Sets a hashbang line (`#!/usr/bin/env node`) to write before a
+    /// `Program::Script`, the leading `#!` is optional. By default this
+    /// will be set to None
+    pub fn hashbang(mut self, value: Option<&str>) -> Self {
+        self.set_hashbang(value);
+        self
+    }
+    /// Sets a hashbang line (`#!/usr/bin/env node`) to write before a
+    /// `Program::Script`, the leading `#!` is optional. By default this
+    /// will be set to None
+    pub fn set_hashbang(&mut self, value: Option<&str>) -> &mut Self {
+        self.hashbang = value.map(|h| h.trim_start_matches("#!").to_string());
+        self
+    }
+    /// Restore all of the options to their default values
+    pub fn reset_to_defaults(&mut self) -> &mut Self {
+        *self = Self::new();
+        self
+    }
+    /// Finalize the writer with the destination, this consumes
+    /// the builder.
+    pub fn build(self, destination: T) -> Writer<T> {
+        let mut writer = Writer::create(destination, self.new_line, self.quote, self.indent);
+        writer.trailing_newline_per_part = self.trailing_newline_per_part;
+        writer.decorators = self.decorators;
+        writer.paren_default_export = self.paren_default_export;
+        writer.empty_class_body_compact = self.empty_class_body_compact;
+        writer.arrow_expression_body = self.arrow_expression_body;
+        writer.generator_star_spacing = self.generator_star_spacing;
+        writer.explicit_nested_ternary = self.explicit_nested_ternary;
+        writer.typescript_mode = self.typescript_mode;
+        writer.multi_line_object_threshold = self.multi_line_object_threshold;
+        writer.multi_line_list_threshold = self.multi_line_list_threshold;
+        writer.minify = self.minify;
+        writer.semicolons = self.semicolons;
+        writer.trailing_commas = self.trailing_commas;
+        writer.brace_style = self.brace_style;
+        writer.hashbang = self.hashbang;
+        writer
+    }
+}
+
+// `derive(Clone)` would require `T: Clone` even
+// though no `T` is ever held by the builder
+impl<T: Write> Clone for Builder<T> {
+    fn clone(&self) -> Self {
+        Self {
+            new_line: self.new_line.clone(),
+            quote: self.quote,
+            indent: self.indent.clone(),
+            trailing_newline_per_part: self.trailing_newline_per_part,
+            decorators: self.decorators,
+            paren_default_export: self.paren_default_export,
+            empty_class_body_compact: self.empty_class_body_compact,
+            arrow_expression_body: self.arrow_expression_body,
+            generator_star_spacing: self.generator_star_spacing,
+            explicit_nested_ternary: self.explicit_nested_ternary,
+            typescript_mode: self.typescript_mode,
+            multi_line_object_threshold: self.multi_line_object_threshold,
+            multi_line_list_threshold: self.multi_line_list_threshold,
+            minify: self.minify,
+            semicolons: self.semicolons,
+            trailing_commas: self.trailing_commas,
+            brace_style: self.brace_style,
+            hashbang: self.hashbang.clone(),
+            p: self.p,
+        }
     }
 }
 
@@ -104,28 +577,92 @@ impl<T: Write> Writer<T> {
     pub fn create(out: T, new_line: String, quote: Option<char>, indent: String) -> Self {
         Self {
             current_indent: 0,
-            at_top_level: true,
-            in_for_init: false,
+            bytes_written: 0,
+            current_line: 0,
+            current_column: 0,
+            source_map: None,
+            context: vec![WriterContext::TopLevel],
             out,
             new_line,
             quote,
             indent,
+            trailing_newline_per_part: true,
+            decorators: false,
+            paren_default_export: false,
+            empty_class_body_compact: false,
+            arrow_expression_body: false,
+            generator_star_spacing: GeneratorStarSpacing::AfterFunction,
+            explicit_nested_ternary: false,
+            typescript_mode: false,
+            multi_line_object_threshold: usize::MAX,
+            multi_line_list_threshold: usize::MAX,
+            minify: false,
+            semicolons: SemicolonPolicy::Always,
+            trailing_commas: TrailingCommaPolicy::Never,
+            brace_style: BraceStyle::SameLine,
+            hashbang: None,
         }
     }
     /// Create a `Builder` for constructing your writer
     pub fn builder() -> Builder<T> {
-        Builder {
-            new_line: String::from("\n"),
-            quote: None,
-            indent: " ".repeat(4),
-            p: ::std::marker::PhantomData,
+        Builder::new()
+    }
+    /// Start recording a `SourceMap` for the provided source file,
+    /// positions are added with `add_source_mapping`
+    /// ```
+    /// use resast::prelude::*;
+    /// use resw::Writer;
+    ///
+    /// let mut writer = Writer::new(Vec::new()).with_source_map("in.js");
+    /// writer.add_source_mapping(0, 0);
+    /// writer.write_stmt(&Stmt::Debugger).unwrap();
+    /// assert_eq!(writer.source_map().unwrap().mappings().len(), 1);
+    /// ```
+    pub fn with_source_map(mut self, source: &str) -> Self {
+        self.source_map = Some(SourceMap::new(source));
+        self
+    }
+    /// The source map recorded so far, if `with_source_map` was used
+    pub fn source_map(&self) -> Option<&SourceMap> {
+        self.source_map.as_ref()
+    }
+    /// Map the current output position to the provided position in the
+    /// original source, resast nodes don't carry their spans so this needs to
+    /// be called before writing a node. Nothing happens without a source map
+    pub fn add_source_mapping(&mut self, source_line: usize, source_column: usize) {
+        let (line, column) = self.position();
+        if let Some(ref mut map) = self.source_map {
+            map.add_mapping(line, column, source_line, source_column);
         }
     }
+    /// Consume the writer, returning the underlying `impl Write`
+    /// ```
+    /// use resast::prelude::*;
+    /// use resw::Writer;
+    ///
+    /// let mut writer = Writer::new(Vec::new());
+    /// writer.write_stmt(&Stmt::Debugger).unwrap();
+    /// let bytes = writer.into_inner();
+    /// assert_eq!(bytes, b"debugger;\n");
+    /// ```
+    pub fn into_inner(self) -> T {
+        self.out
+    }
     /// This will loop over the contents of a `Program` and
     /// attempt write them all to the provided `impl Write`
+    ///
+    /// A hashbang set on the `Builder` is only written for a `Program::Script`
     pub fn write_program(&mut self, program: &Program) -> Res {
         let parts = match program {
-            Program::Script(ref parts) => parts,
+            Program::Script(ref parts) => {
+                if let Some(hashbang) = self.hashbang.clone() {
+                    self.write("#!")?;
+                    self.write(&hashbang)?;
+                    // this has to end the line even if `new_line` is empty
+                    self.write("\n")?;
+                }
+                parts
+            }
             Program::Mod(ref parts) => parts,
         };
         for ref part in parts {
@@ -133,41 +670,183 @@ impl<T: Write> Writer<T> {
         }
         Ok(())
     }
+    /// Count the top level `ProgramPart`s of a `Program`,
+    /// nothing will be written
+    pub fn write_program_part_count(&self, program: &Program) -> usize {
+        match program {
+            Program::Script(ref parts) => parts.len(),
+            Program::Mod(ref parts) => parts.len(),
+        }
+    }
+    /// Estimate the number of bytes `write_program` would produce
+    /// with this writer's options, nothing will be written
+    /// to the underlying `impl Write`
+    pub fn estimate_output_size(&self, program: &Program) -> Result<usize, IoError> {
+        let mut counter = self.options_builder().build(ByteCounter(0));
+        counter.write_program(program)?;
+        Ok(counter.out.0)
+    }
+    /// A `Builder` with all of this writer's options, used
+    /// to create another writer that formats the same way
+    fn options_builder<U: Write>(&self) -> Builder<U> {
+        Builder {
+            new_line: self.new_line.clone(),
+            quote: self.quote,
+            indent: self.indent.clone(),
+            trailing_newline_per_part: self.trailing_newline_per_part,
+            decorators: self.decorators,
+            paren_default_export: self.paren_default_export,
+            empty_class_body_compact: self.empty_class_body_compact,
+            arrow_expression_body: self.arrow_expression_body,
+            generator_star_spacing: self.generator_star_spacing,
+            explicit_nested_ternary: self.explicit_nested_ternary,
+            typescript_mode: self.typescript_mode,
+            multi_line_object_threshold: self.multi_line_object_threshold,
+            multi_line_list_threshold: self.multi_line_list_threshold,
+            minify: self.minify,
+            semicolons: self.semicolons,
+            trailing_commas: self.trailing_commas,
+            brace_style: self.brace_style,
+            hashbang: self.hashbang.clone(),
+            p: ::std::marker::PhantomData,
+        }
+    }
+    /// Apply the provided options while running `f`, the writer's
+    /// original options will be restored once `f` returns
+    /// ```
+    /// use resw::{Writer, WriterOptions};
+    /// let mut w = Writer::new(Vec::new());
+    /// let compact = WriterOptions {
+    ///     indent: Some(String::new()),
+    ///     new_line: Some(String::new()),
+    ///     quote: None,
+    /// };
+    /// w.with_options(&compact, |w| w.write_block_stmt(&[])).unwrap();
+    /// ```
+    pub fn with_options<F, R>(&mut self, opts: &WriterOptions, f: F) -> R
+    where
+        F: FnOnce(&mut Writer<T>) -> R,
+    {
+        let indent = self.indent.clone();
+        let new_line = self.new_line.clone();
+        let quote = self.quote;
+        if let Some(ref i) = opts.indent {
+            self.indent = i.clone();
+        }
+        if let Some(ref n) = opts.new_line {
+            self.new_line = n.clone();
+        }
+        if opts.quote.is_some() {
+            self.quote = opts.quote;
+        }
+        let ret = f(self);
+        self.indent = indent;
+        self.new_line = new_line;
+        self.quote = quote;
+        ret
+    }
+    /// The innermost context the writer is currently in
+    pub fn context(&self) -> WriterContext {
+        self.context
+            .last()
+            .cloned()
+            .unwrap_or(WriterContext::TopLevel)
+    }
+    /// Check if the writer is currently anywhere inside of the provided context
+    pub fn in_context(&self, context: WriterContext) -> bool {
+        self.context.contains(&context)
+    }
+    /// The number of bytes written to the underlying `impl Write` so far,
+    /// this is the offset the next write will start at
+    pub fn byte_offset(&self) -> usize {
+        self.bytes_written
+    }
+    /// The zero based `(line, column)` the next write will start at,
+    /// the column is counted in UTF-16 code units to match the
+    /// columns of a source map v3
+    pub fn position(&self) -> (usize, usize) {
+        (self.current_line, self.current_column)
+    }
+    /// The number of levels the writer is currently indented
+    pub fn indent_level(&self) -> usize {
+        self.current_indent
+    }
+    /// Set the number of levels the writer is indented, this can be used
+    /// to save and restore the level around calls from an outer formatter.
+    /// Setting it below the number of open braces leaves the closing
+    /// braces that remain at no indentation
+    pub fn set_indent_level(&mut self, level: usize) {
+        self.current_indent = level;
+    }
+    /// The string written once for each level of indentation
+    pub fn indent_string(&self) -> &str {
+        &self.indent
+    }
+    /// The full leading whitespace for the current indentation level
+    pub fn current_indent_str(&self) -> String {
+        self.indent.repeat(self.current_indent)
+    }
     /// This will attempt to write a single `ProgramPart`
+    ///
+    /// The part will be followed by a new line unless
+    /// `trailing_newline_per_part` was disabled on the `Builder`
     pub fn write_part(&mut self, part: &ProgramPart) -> Res {
         trace!("write_part: {:#?}", part);
-        self.at_top_level = true;
+        self.context.clear();
+        self.context.push(WriterContext::TopLevel);
         self._write_part(part)?;
-        self.write_new_line()?;
+        if self.trailing_newline_per_part {
+            self.write_new_line()?;
+        }
         Ok(())
     }
-    /// Internal program part writer to help with top level
-    /// detection, new lines and whitespace writing
+    /// Internal program part writer to help with
+    /// new lines and whitespace writing
     fn _write_part(&mut self, part: &ProgramPart) -> Res {
         trace!("_write_part");
         self.write_leading_whitespace()?;
         match part {
             ProgramPart::Decl(decl) => self.write_decl(decl)?,
             ProgramPart::Dir(dir) => self.write_directive(dir)?,
-            ProgramPart::Stmt(stmt) => self.write_stmt(stmt)?,
+            ProgramPart::Stmt(stmt) => {
+                // without semicolons a statement starting with one of these
+                // would continue the previous statement instead, this is
+                // only done here since the body of an `if` or a loop isn't
+                // preceded by another statement
+                if let Stmt::Expr(ref e) = stmt {
+                    if self.omit_semicolons()
+                        && (Self::expr_stmt_needs_wrap(e) || Self::starts_with_asi_hazard(e))
+                    {
+                        self.write_empty_stmt()?;
+                    }
+                }
+                self.write_stmt(stmt)?
+            }
+        }
+        // a block is also used as the body of other statements so it
+        // doesn't end its own line, the next part needs to start on a new one
+        if let ProgramPart::Stmt(Stmt::Block(_)) = part {
+            self.write_new_line()?;
         }
         Ok(())
     }
     /// Attempt to write a `Declaration` to the `impl Write`
+    ///
+    /// Class and function declarations are followed by a single
+    /// new line, this includes when they are the target of an export
     pub fn write_decl(&mut self, decl: &Decl) -> Res {
         trace!("write_decl");
         match decl {
             Decl::Variable(ref kind, ref decls) => self.write_variable_decls(kind, decls)?,
             Decl::Class(ref class) => {
-                self.at_top_level = false;
                 self.write_class(class)?;
                 self.write_new_line()?;
             }
             Decl::Function(ref func) => {
-                self.at_top_level = false;
                 self.write_function(func)?;
                 self.write_new_line()?;
             }
+            // both of these write their own semicolon
             Decl::Export(ref exp) => self.write_export_decl(exp)?,
             Decl::Import(ref imp) => self.write_import_decl(imp)?,
         };
@@ -191,12 +870,16 @@ impl<T: Write> Writer<T> {
             }
             self.write_variable_decl(decl)?;
         }
-        self.write_empty_stmt()?;
+        self.write_stmt_end()?;
         self.write_new_line()
     }
     /// Attempt to write a `Class` to the `impl Write`, used for both
     /// writing the contents of `Declaration::Class` and `Expr::Class`
     ///
+    /// No new line is written after the closing brace, `write_decl`
+    /// adds one for declarations while a class expression relies on the
+    /// enclosing statement to end the line
+    ///
     /// ```js
     /// // class expression
     /// let x = class {
@@ -219,27 +902,166 @@ impl<T: Write> Writer<T> {
     /// ```
     pub fn write_class(&mut self, class: &Class) -> Res {
         trace!("write_class");
-        self.write("class ")?;
+        self._write_class(class, &[])
+    }
+    /// Attempt to write a `Class` along with its decorators, the
+    /// decorators will only be written if enabled on the `Builder`
+    /// ```js
+    /// @sealed
+    /// class Y {
+    ///     @log
+    ///     method1() {
+    ///     }
+    /// }
+    /// ```
+    pub fn write_decorated_class(&mut self, class: &Class, decorators: &ClassDecorators) -> Res {
+        trace!("write_decorated_class");
+        if !self.decorators {
+            return self.write_class(class);
+        }
+        for ref dec in &decorators.class {
+            self.write_decorator(dec)?;
+            self.write_new_line()?;
+            self.write_leading_whitespace()?;
+        }
+        self._write_class(class, &decorators.members)
+    }
+    /// Internal class writer, `member_decorators` are
+    /// matched to the class body by index
+    fn _write_class(&mut self, class: &Class, member_decorators: &[Vec<Expr>]) -> Res {
+        self.write("class")?;
         if let Some(ref id) = class.id {
-            self.write_ident(id)?;
             self.write(" ")?;
+            self.write_ident(id)?;
         }
         if let Some(ref ex) = class.super_class {
-            self.write("extends ")?;
-            self.write_expr(ex)?;
-            self.write(" ")?;
+            self.write(" extends ")?;
+            // only a left hand side expression is allowed after `extends`
+            match &**ex {
+                Expr::Logical(_)
+                | Expr::Binary(_)
+                | Expr::Conditional(_)
+                | Expr::Assignment(_)
+                | Expr::ArrowFunction(_)
+                | Expr::Unary(_)
+                | Expr::Update(_)
+                | Expr::Await(_)
+                | Expr::Yield(_) => self.write_wrapped_expr(ex)?,
+                _ => self.write_single_expr(ex)?,
+            }
+        }
+        self.write_space_before_brace()?;
+        if self.empty_class_body_compact && class.body.is_empty() {
+            self.write_brace_line_break()?;
+            return self.write("{ }");
         }
+        self.context.push(WriterContext::ClassBody);
         self.write_open_brace()?;
         self.write_new_line()?;
-        for ref part in &class.body {
-            self.write_new_line()?;
+        for (i, ref part) in class.body.iter().enumerate() {
+            // members are separated by a blank line
+            if i > 0 {
+                self.write_new_line()?;
+            }
             self.write_leading_whitespace()?;
+            if let Some(decorators) = member_decorators.get(i) {
+                for ref dec in decorators {
+                    self.write_decorator(dec)?;
+                    self.write_new_line()?;
+                    self.write_leading_whitespace()?;
+                }
+            }
             self.write_property(part)?;
             self.write_new_line()?;
         }
         self.write_close_brace()?;
+        self.context.pop();
+        Ok(())
+    }
+    /// Attempt to write a class member with the `static` keyword,
+    /// resast's `Property` has no flag for this so it has to be called
+    /// directly when writing a class body
+    /// ```js
+    /// static thing() {
+    /// }
+    /// ```
+    pub fn write_static_property(&mut self, prop: &Property) -> Res {
+        trace!("write_static_property");
+        self.write("static ")?;
+        self.write_property(prop)
+    }
+    /// Attempt to write a static initialization block of a class body
+    /// ```js
+    /// static {
+    ///     this.count = 0;
+    /// }
+    /// ```
+    pub fn write_static_block(&mut self, body: &[ProgramPart]) -> Res {
+        trace!("write_static_block");
+        self.write("static")?;
+        self.write_space_before_brace()?;
+        self.write_block_stmt(body)
+    }
+    /// Attempt to write a TypeScript interface, nothing will
+    /// be written unless enabled on the `Builder`
+    /// ```ts
+    /// interface Foo {
+    ///     bar(): void;
+    /// }
+    /// ```
+    pub fn write_interface(&mut self, iface: &Interface) -> Res {
+        trace!("write_interface");
+        if !self.typescript_mode {
+            return Ok(());
+        }
+        self.write("interface ")?;
+        self.write_ident(&iface.name)?;
+        self.write_space_before_brace()?;
+        self.write_open_brace()?;
+        self.write_new_line()?;
+        for member in &iface.members {
+            self.write_leading_whitespace()?;
+            self.write(&member.name)?;
+            self.write(": ")?;
+            self.write(&member.type_annotation)?;
+            self.write_empty_stmt()?;
+            self.write_new_line()?;
+        }
+        self.write_close_brace()?;
         Ok(())
     }
+    /// Attempt to write a single decorator, anything other than
+    /// a dotted chain of identifiers or a call of one will be
+    /// wrapped in parentheses
+    /// ```js
+    /// @log
+    /// @a.b.c()
+    /// @(decorators[0])
+    /// ```
+    pub fn write_decorator(&mut self, expr: &Expr) -> Res {
+        trace!("write_decorator");
+        self.write("@")?;
+        let unwrapped = match expr {
+            Expr::Call(ref call) => Self::is_dotted_ident_chain(&call.callee),
+            _ => Self::is_dotted_ident_chain(expr),
+        };
+        if unwrapped {
+            self.write_expr(expr)
+        } else {
+            self.write_wrapped_expr(expr)
+        }
+    }
+    /// Check if an expression is an identifier or
+    /// non-computed member access of one (`a.b.c`)
+    fn is_dotted_ident_chain(expr: &Expr) -> bool {
+        match expr {
+            Expr::Ident(_) => true,
+            Expr::Member(ref m) if !m.computed => {
+                Self::is_dotted_ident_chain(&m.object) && matches!(&*m.property, Expr::Ident(_))
+            }
+            _ => false,
+        }
+    }
     /// Attempt to write the contents of `Declaration::Export` to the `impl Write`
     /// ```js
     /// export function Thing() {
@@ -259,25 +1081,49 @@ impl<T: Write> Writer<T> {
     }
     /// Attempt to write the contents `ModuleExport::All` to the `impl Write`
     /// ```js
-    /// export * from 'module'
+    /// export * from 'module';
     /// ```
     pub fn write_all_export(&mut self, exp: &Literal) -> Res {
         trace!("write_all_export");
         self.write("* from ")?;
         self.write_literal(exp)?;
+        self.write_stmt_end()?;
+        Ok(())
+    }
+    /// Attempt to write a namespace re-export, resast's `ModExport::All`
+    /// can't carry a name so this has to be called directly after
+    /// writing `export `
+    /// ```js
+    /// export * as utils from 'module';
+    /// ```
+    pub fn write_all_as_export(&mut self, name: &str, exp: &Literal) -> Res {
+        trace!("write_all_as_export");
+        self.write("* as ")?;
+        self.write_ident(name)?;
+        self.write(" from ")?;
+        self.write_literal(exp)?;
+        self.write_stmt_end()?;
         Ok(())
     }
     /// Attempt to write the contents `ModuleExport::Default` to the `impl Write`
     /// ```js
     /// export default function Thing() {
     /// }
+    /// export default 42;
     /// ```
     pub fn write_default_export(&mut self, exp: &DefaultExportDecl) -> Res {
         trace!("write_default_export");
         self.write("default ")?;
         match exp {
             DefaultExportDecl::Decl(ref d) => self.write_decl(d)?,
-            DefaultExportDecl::Expr(ref e) => self.write_expr(e)?,
+            DefaultExportDecl::Expr(ref e) => {
+                match e {
+                    Expr::Function(_) | Expr::Class(_) => self.write_expr(e)?,
+                    _ if self.paren_default_export => self.write_wrapped_expr(e)?,
+                    _ => self.write_single_expr(e)?,
+                }
+                self.write_stmt_end()?;
+            }
         }
         Ok(())
     }
@@ -295,33 +1141,54 @@ impl<T: Write> Writer<T> {
     }
     /// Attempts to write the contents of `NamedExportDecl::Specifier` to the `impl Write`
     /// ```js
-    /// export {Stuff as Things} from 'module'
-    /// export {Places} from 'other_module'
+    /// export {Stuff as Things} from 'module';
+    /// export {Places} from 'other_module';
+    /// export {Thing};
+    /// export {};
     /// ```
+    /// The specifiers are written exactly as provided, duplicate
+    /// names are not removed or reported
     pub fn write_export_specifiers(
         &mut self,
         specifiers: &[ExportSpecifier],
         from: &Option<Literal>,
     ) -> Res {
         trace!("write_export_specifiers");
-        self.write("{")?;
-        let mut after_first = false;
-        for s in specifiers {
-            if after_first {
-                self.write(", ")?;
-            }
-            self.write_ident(&s.local)?;
-            if let Some(ref name) = &s.exported {
-                self.write(" as ")?;
-                self.write(name)?;
+        if specifiers.len() > self.multi_line_list_threshold {
+            let trailing_comma = self.trailing_commas != TrailingCommaPolicy::Never;
+            self.write_multi_line_list("{", "}", specifiers, trailing_comma, |w, s| {
+                w.write_export_specifier(s)
+            })?;
+        } else {
+            self.write("{")?;
+            let mut after_first = false;
+            for s in specifiers {
+                if after_first {
+                    self.write(", ")?;
+                }
+                self.write_export_specifier(s)?;
+                after_first = true;
             }
-            after_first = true;
+            self.write("}")?;
         }
-        self.write("}")?;
         if let Some(ref from) = from {
             self.write(" from ")?;
             self.write_literal(from)?;
         }
+        self.write_stmt_end()?;
+        Ok(())
+    }
+    /// Attempts to write a single `ExportSpecifier`
+    /// ```js
+    /// export {Stuff as Things};
+    /// ```
+    pub fn write_export_specifier(&mut self, spec: &ExportSpecifier) -> Res {
+        trace!("write_export_specifier");
+        self.write_ident(&spec.local)?;
+        if let Some(ref name) = &spec.exported {
+            self.write(" as ")?;
+            self.write(name)?;
+        }
         Ok(())
     }
     /// Attempts to write the contents of `Declaration::Import` to the `impl Write`
@@ -329,50 +1196,52 @@ impl<T: Write> Writer<T> {
     /// import * as Moment from 'moment';
     /// import {Thing, Place} from 'module';
     /// import Stuff from 'other_module';
+    /// import 'side_effect';
     /// ```
     pub fn write_import_decl(&mut self, imp: &ModImport) -> Res {
         trace!("write_import_decl");
         self.write("import ")?;
-        if imp.specifiers.len() == 0 {
-            self.write("{}")?;
+        if imp.specifiers.is_empty() {
+            self.write_literal(&imp.source)?;
+            return self.write_stmt_end();
         }
-        let mut opened_brace = false;
-        let mut specifiers = imp.specifiers.iter();
-        if let Some(ref first) = specifiers.next() {
-            if let ImportSpecifier::Default(ref ident) = first {
-                self.write_ident(ident)?;
-            } else if let ImportSpecifier::Namespace(ref imp) = first {
-                self.write_namespace_import(imp)?;
-            } else {
-                self.write("{ ")?;
-                opened_brace = true;
-                self.write_import_specificer(first)?;
+        // a default or namespace import always comes before the braces
+        let mut after_first = false;
+        let mut normal = Vec::new();
+        for spec in &imp.specifiers {
+            if let ImportSpecifier::Normal(_, _) = spec {
+                normal.push(spec);
+                continue;
+            }
+            if after_first {
+                self.write(", ")?;
             }
+            self.write_import_specifier(spec)?;
+            after_first = true;
         }
-
-        if !opened_brace {
-            if let Some(ref next) = specifiers.next() {
-                if let ImportSpecifier::Namespace(ref name) = next {
-                    self.write(", ")?;
-                    self.write_namespace_import(name)?;
-                } else {
-                    self.write(", { ")?;
-                    self.write_import_specificer(next)?;
-                    opened_brace = true;
+        if !normal.is_empty() {
+            if after_first {
+                self.write(", ")?;
+            }
+            if normal.len() > self.multi_line_list_threshold {
+                let trailing_comma = self.trailing_commas != TrailingCommaPolicy::Never;
+                self.write_multi_line_list("{", "}", &normal, trailing_comma, |w, spec| {
+                    w.write_import_specifier(spec)
+                })?;
+            } else {
+                self.write("{ ")?;
+                for (i, spec) in normal.iter().enumerate() {
+                    if i > 0 {
+                        self.write(", ")?;
+                    }
+                    self.write_import_specifier(spec)?;
                 }
+                self.write(" }")?;
             }
         }
-
-        while let Some(ref s) = specifiers.next() {
-            self.write(", ")?;
-            self.write_import_specificer(s)?;
-        }
-        if opened_brace {
-            self.write(" }")?;
-        }
         self.write(" from ")?;
         self.write_literal(&imp.source)?;
-        self.write_empty_stmt()?;
+        self.write_stmt_end()?;
         Ok(())
     }
     /// Attempts to write a single `ImportSpecifier` to the `impl Write`
@@ -381,8 +1250,8 @@ impl<T: Write> Writer<T> {
     /// import {Thing, Place} from 'module';
     /// import Stuff from 'other_module';
     /// ```
-    pub fn write_import_specificer(&mut self, spec: &ImportSpecifier) -> Res {
-        trace!("write_import_specificer");
+    pub fn write_import_specifier(&mut self, spec: &ImportSpecifier) -> Res {
+        trace!("write_import_specifier");
         match spec {
             ImportSpecifier::Default(ref i) => self.write_ident(i)?,
             ImportSpecifier::Namespace(ref n) => self.write_namespace_import(n)?,
@@ -390,6 +1259,11 @@ impl<T: Write> Writer<T> {
         }
         Ok(())
     }
+    /// Misspelled name of `write_import_specifier`
+    #[deprecated(since = "0.2.3", note = "Use write_import_specifier instead")]
+    pub fn write_import_specificer(&mut self, spec: &ImportSpecifier) -> Res {
+        self.write_import_specifier(spec)
+    }
     /// Attempts to write the contents of`ImportSpecifier::Namespace` to the `impl Write`
     /// ```js
     /// import * as Moment from 'moment';
@@ -420,7 +1294,7 @@ impl<T: Write> Writer<T> {
     pub fn write_directive(&mut self, dir: &Dir) -> Res {
         trace!("write_directive");
         self.write_literal(&dir.expr)?;
-        self.write_empty_stmt()?;
+        self.write_stmt_end()?;
         self.write_new_line()?;
         Ok(())
     }
@@ -435,7 +1309,7 @@ impl<T: Write> Writer<T> {
         self.write_pattern(&decl.id)?;
         if let Some(ref init) = decl.init {
             self.write(" = ")?;
-            self.write_expr(init)?;
+            self.write_single_expr(init)?;
         }
         Ok(())
     }
@@ -450,44 +1324,54 @@ impl<T: Write> Writer<T> {
         self.write(s)
     }
     /// Attempts to write the contents of a `Stmt`
+    ///
+    /// Every `Stmt` variant is matched explicitly, no catch-all arm
+    /// should be added here so a new variant fails to compile instead
+    /// of panicking.
+    ///
+    /// `Stmt::Var` is what RESSA produces for a `var` declaration at the statement
+    /// level while `let` and `const` are produced as `Decl::Variable`, so both
+    /// will need to be handled when walking a `Program`
+    #[deny(unreachable_patterns)]
     pub fn write_stmt(&mut self, stmt: &Stmt) -> Res {
         trace!("write_stmt");
         let mut semi = true;
         let mut new_line = true;
-        let cached_state = self.at_top_level;
         match stmt {
             Stmt::Empty => {
                 new_line = false;
             }
             Stmt::Debugger => self.write_debugger_stmt()?,
             Stmt::Expr(ref stmt) => {
-                let wrap = match stmt {
-                    Expr::Literal(_) | Expr::Object(_) | Expr::Function(_) | Expr::Binary(_) => {
-                        true
-                    }
-                    _ => false,
-                };
-                if wrap {
+                if Self::expr_stmt_needs_wrap(stmt) {
                     self.write_wrapped_expr(stmt)?
                 } else {
                     self.write_expr(stmt)?
                 }
             }
             Stmt::Block(ref stmt) => {
-                self.at_top_level = false;
                 self.write_block_stmt(stmt)?;
                 semi = false;
                 new_line = false;
-                self.at_top_level = cached_state;
             }
             Stmt::With(ref stmt) => {
                 self.write_with_stmt(stmt)?;
                 semi = false;
+                // any other body will have already ended the line
+                new_line = match &*stmt.body {
+                    Stmt::Empty | Stmt::Block(_) => true,
+                    _ => false,
+                };
             }
             Stmt::Return(ref stmt) => self.write_return_stmt(stmt)?,
             Stmt::Labeled(ref stmt) => {
                 self.write_labeled_stmt(stmt)?;
                 semi = false;
+                // any other body will have already ended the line
+                new_line = match &*stmt.body {
+                    Stmt::Empty | Stmt::Block(_) => true,
+                    _ => false,
+                };
             }
             Stmt::Break(ref stmt) => self.write_break_stmt(stmt)?,
             Stmt::Continue(ref stmt) => self.write_continue_stmt(stmt)?,
@@ -496,7 +1380,6 @@ impl<T: Write> Writer<T> {
                 semi = false;
             }
             Stmt::Switch(ref stmt) => {
-                self.at_top_level = false;
                 self.write_switch_stmt(stmt)?;
                 semi = false;
             }
@@ -511,31 +1394,152 @@ impl<T: Write> Writer<T> {
             }
             Stmt::DoWhile(ref stmt) => self.write_do_while_stmt(stmt)?,
             Stmt::For(ref stmt) => {
-                self.at_top_level = false;
                 new_line = self.write_for_stmt(stmt)?;
                 semi = false;
             }
             Stmt::ForIn(ref stmt) => {
-                self.at_top_level = false;
                 new_line = self.write_for_in_stmt(stmt)?;
                 semi = false;
             }
             Stmt::ForOf(ref stmt) => {
-                self.at_top_level = false;
                 new_line = self.write_for_of_stmt(stmt)?;
                 semi = false;
             }
             Stmt::Var(ref stmt) => self.write_var_stmt(stmt)?,
         };
         if semi {
-            self.write_empty_stmt()?;
+            // an empty statement is only its semicolon
+            if let Stmt::Empty = stmt {
+                self.write_empty_stmt()?;
+            } else {
+                self.write_stmt_end()?;
+            }
         }
         if new_line {
             self.write_new_line()?;
         }
-        self.at_top_level = cached_state;
         Ok(())
     }
+    /// Check if the expression of an expression statement needs to be wrapped
+    /// in parentheses.
+    ///
+    /// A string literal statement at the start of a body would
+    /// be re-parsed as a directive (`'use strict'`) so literals
+    /// are always wrapped to keep them as expressions
+    fn expr_stmt_needs_wrap(expr: &Expr) -> bool {
+        match expr {
+            Expr::Literal(_) | Expr::Object(_) | Expr::Function(_) => true,
+            Expr::Binary(ref bin) => Self::starts_with_brace_or_function(&bin.left),
+            Expr::Sequence(ref seq) => match seq.first() {
+                Some(first) => Self::starts_with_brace_or_function(first),
+                None => false,
+            },
+            _ => false,
+        }
+    }
+    /// Check if an expression written at the start of a statement
+    /// would be confused with a block, function or class declaration
+    fn starts_with_brace_or_function(expr: &Expr) -> bool {
+        match expr {
+            Expr::Object(_) | Expr::Function(_) | Expr::Class(_) => true,
+            Expr::Binary(ref bin) => Self::starts_with_brace_or_function(&bin.left),
+            Expr::Sequence(ref seq) => match seq.first() {
+                Some(first) => Self::starts_with_brace_or_function(first),
+                None => false,
+            },
+            _ => false,
+        }
+    }
+    /// Check if an expression will be written starting with a `(`, `[`,
+    /// `` ` ``, `/`, `+` or `-`, none of these end the previous statement
+    /// when semicolons are omitted
+    fn starts_with_asi_hazard(expr: &Expr) -> bool {
+        match expr {
+            Expr::Array(_)
+            | Expr::Sequence(_)
+            | Expr::Literal(Literal::Template(_))
+            | Expr::Literal(Literal::RegEx(_)) => true,
+            Expr::ArrowFunction(ref arrow) => match arrow.params.as_slice() {
+                [FunctionArg::Pat(Pat::Identifier(_))] | [FunctionArg::Expr(Expr::Ident(_))] => {
+                    false
+                }
+                _ => true,
+            },
+            Expr::Binary(ref bin) => Self::starts_with_asi_hazard(&bin.left),
+            Expr::Logical(ref logical) => Self::starts_with_asi_hazard(&logical.left),
+            Expr::Conditional(ref cond) => Self::starts_with_asi_hazard(&cond.test),
+            Expr::Call(ref call) => match &*call.callee {
+                Expr::Function(_) | Expr::ArrowFunction(_) => true,
+                callee => Self::starts_with_asi_hazard(callee),
+            },
+            // the same objects `write_member_object` wraps
+            Expr::Member(ref member) => match &*member.object {
+                Expr::Assignment(_)
+                | Expr::Literal(Literal::Number(_))
+                | Expr::Conditional(_)
+                | Expr::Logical(_)
+                | Expr::Function(_)
+                | Expr::ArrowFunction(_)
+                | Expr::Object(_)
+                | Expr::Binary(_)
+                | Expr::Unary(_)
+                | Expr::Update(_) => true,
+                object => Self::starts_with_asi_hazard(object),
+            },
+            Expr::TaggedTemplate(ref tagged) => Self::starts_with_asi_hazard(&tagged.tag),
+            // the same left sides `write_assignment_expr` wraps
+            Expr::Assignment(ref assign) => {
+                Self::assignment_left_needs_wrap(&assign.left)
+                    || match &assign.left {
+                        AssignmentLeft::Expr(ref left) => Self::starts_with_asi_hazard(left),
+                        AssignmentLeft::Pat(_) => false,
+                    }
+            }
+            Expr::Update(ref update) => {
+                update.prefix || Self::starts_with_asi_hazard(&update.argument)
+            }
+            _ => Self::starts_with_sign(expr),
+        }
+    }
+    /// Check if an expression will be written starting with a `+` or `-`
+    fn starts_with_sign(expr: &Expr) -> bool {
+        match expr {
+            Expr::Unary(ref unary) => {
+                unary.prefix
+                    && (unary.operator == UnaryOperator::Minus
+                        || unary.operator == UnaryOperator::Plus)
+            }
+            Expr::Update(ref update) => update.prefix,
+            Expr::Binary(ref bin) => Self::starts_with_sign(&bin.left),
+            _ => false,
+        }
+    }
+    /// Check if an expression would be written starting with `!`
+    fn starts_with_not(expr: &Expr) -> bool {
+        match expr {
+            Expr::Unary(ref unary) => unary.prefix && unary.operator == UnaryOperator::Not,
+            Expr::Binary(ref bin) => Self::starts_with_not(&bin.left),
+            _ => false,
+        }
+    }
+    /// Check if an expression would be written ending with a postfix `--`
+    fn ends_with_decrement(expr: &Expr) -> bool {
+        match expr {
+            Expr::Update(ref update) => {
+                !update.prefix && update.operator == UpdateOperator::Decrement
+            }
+            Expr::Binary(ref bin) => Self::ends_with_decrement(&bin.right),
+            _ => false,
+        }
+    }
+    /// Write the space on either side of an operator,
+    /// nothing is written when minifying
+    fn write_operator_space(&mut self) -> Res {
+        if self.minify {
+            return Ok(());
+        }
+        self.write(" ")
+    }
     /// Attempts to write a debugger stmt
     /// ```js
     /// debugger;
@@ -552,6 +1556,7 @@ impl<T: Write> Writer<T> {
     /// ```
     pub fn write_block_stmt(&mut self, block: &[ProgramPart]) -> Res {
         trace!("write_block_stmt");
+        self.context.push(WriterContext::BlockStatement);
         self.write_open_brace()?;
         if block.len() == 0 {
             self.write_new_line()?;
@@ -563,6 +1568,7 @@ impl<T: Write> Writer<T> {
             self._write_part(part)?;
         }
         self.write_close_brace()?;
+        self.context.pop();
         Ok(())
     }
     /// Attempts to write a `WithStmt`
@@ -575,7 +1581,8 @@ impl<T: Write> Writer<T> {
         trace!("write_with_stmt");
         self.write("with (")?;
         self.write_expr(&expr.object)?;
-        self.write(") ")?;
+        self.write(")")?;
+        self.write_space_before_stmt(&expr.body)?;
         self.write_stmt(&expr.body)?;
         Ok(())
     }
@@ -604,12 +1611,19 @@ impl<T: Write> Writer<T> {
     ///         break label;
     ///     }
     /// }
+    /// empty: ;
     /// ```
     pub fn write_labeled_stmt(&mut self, expr: &LabeledStmt) -> Res {
         trace!("write_labeled_stmt");
         self.write_ident(&expr.label)?;
-        self.write(": ")?;
-        self.write_stmt(&expr.body)?;
+        self.write(":")?;
+        if let Stmt::Empty = &*expr.body {
+            self.write(" ")?;
+            self.write_empty_stmt()?;
+        } else {
+            self.write_space_before_stmt(&expr.body)?;
+            self.write_stmt(&expr.body)?;
+        }
         Ok(())
     }
     /// Attempts to write a break statement
@@ -668,17 +1682,21 @@ impl<T: Write> Writer<T> {
         trace!("write_if_stmt");
         self.write("if (")?;
         self.write_expr(&expr.test)?;
-        self.write(") ")?;
+        self.write(")")?;
         if let Stmt::Empty = &*expr.consequent {
+            self.write_space_before_brace()?;
             self.write_block_stmt(&[])?;
         } else {
+            self.write_space_before_stmt(&expr.consequent)?;
             self.write_stmt(&expr.consequent)?;
         }
         if let Some(ref alt) = &expr.alternate {
-            self.write(" else ")?;
+            self.write(" else")?;
             if let Stmt::Empty = &**alt {
+                self.write_space_before_brace()?;
                 self.write_block_stmt(&[])?;
             } else {
+                self.write_space_before_stmt(alt)?;
                 self.write_stmt(alt)?;
             }
         }
@@ -695,8 +1713,10 @@ impl<T: Write> Writer<T> {
         trace!("write_switch_stmt");
         self.write("switch (")?;
         self.write_expr(&switch.discriminant)?;
-        self.write(") ")?;
+        self.write(")")?;
+        self.write_space_before_brace()?;
         if switch.cases.len() == 0 {
+            self.write_brace_line_break()?;
             self.write("{ }")?;
             return Ok(());
         }
@@ -770,21 +1790,27 @@ impl<T: Write> Writer<T> {
     ///
     /// }
     /// ```
+    /// The catch clause is skipped entirely when there is no
+    /// `handler` (`try {} finally {}`) and the parens are
+    /// skipped when the handler has no `param` (`catch {}`)
     pub fn write_try_stmt(&mut self, stmt: &TryStmt) -> Res {
         trace!("write_try_stmt");
-        self.write("try ")?;
+        self.write("try")?;
+        self.write_space_before_brace()?;
         self.write_block_stmt(&stmt.block)?;
         if let Some(ref c) = &stmt.handler {
             self.write(" catch")?;
             if let Some(ref param) = &c.param {
                 self.write(" (")?;
                 self.write_pattern(param)?;
-                self.write(") ")?;
+                self.write(")")?;
             }
+            self.write_space_before_brace()?;
             self.write_block_stmt(&c.body)?;
         }
         if let Some(ref f) = &stmt.finalizer {
-            self.write(" finally ")?;
+            self.write(" finally")?;
+            self.write_space_before_brace()?;
             self.write_block_stmt(&f)?;
         }
         Ok(())
@@ -796,15 +1822,10 @@ impl<T: Write> Writer<T> {
     /// ```
     pub fn write_while_stmt(&mut self, stmt: &WhileStmt) -> Result<bool, IoError> {
         trace!("write_while_stmt");
-        let mut ret = false;
         self.write("while (")?;
         self.write_expr(&stmt.test)?;
-        self.write(") ")?;
-        if let Stmt::Block(_) = &*stmt.body {
-            ret = true;
-        }
-        self.write_stmt(&stmt.body)?;
-        Ok(ret)
+        self.write(")")?;
+        self.write_loop_body(&stmt.body)
     }
     /// Attempts to write a do while statement
     /// ```js
@@ -817,7 +1838,7 @@ impl<T: Write> Writer<T> {
         if let Stmt::Empty = &*stmt.body {
             self.write("; ")?;
         } else {
-            self.write(" ")?;
+            self.write_space_before_stmt(&stmt.body)?;
             self.write_stmt(&stmt.body)?;
             self.write(" ")?;
         }
@@ -845,18 +1866,20 @@ impl<T: Write> Writer<T> {
         if let Some(ref update) = &stmt.update {
             self.write_expr(update)?;
         }
-        self.write(") ")?;
-        let ret = if let Stmt::Block(_) = &*stmt.body {
-            true
-        } else {
-            false
-        };
-        self.write_stmt(&stmt.body)?;
-        Ok(ret)
+        self.write(")")?;
+        self.write_loop_body(&stmt.body)
     }
     /// Attempts to write the first part of a c-style for loop's parenthetical
+    ///
+    /// Any `in` binary expression written while in here will be wrapped in
+    /// parentheses, no matter how deeply it is nested, so it can't be confused
+    /// with a for in loop
+    /// ```js
+    /// for (var x = (a in b) ? a : b;;) {
+    /// }
+    /// ```
     pub fn write_loop_init(&mut self, init: &LoopInit) -> Res {
-        self.in_for_init = true;
+        self.context.push(WriterContext::ForInit);
         match init {
             LoopInit::Expr(ref e) => self.write_expr(e)?,
             LoopInit::Variable(ref kind, ref v) => {
@@ -871,7 +1894,7 @@ impl<T: Write> Writer<T> {
                 }
             }
         }
-        self.in_for_init = false;
+        self.context.pop();
         Ok(())
     }
     /// Attempts to write a for in loop
@@ -886,30 +1909,47 @@ impl<T: Write> Writer<T> {
         self.write_loop_left(&stmt.left)?;
         self.write(" in ")?;
         self.write_expr(&stmt.right)?;
-        self.write(") ")?;
-        self.write_stmt(&stmt.body)?;
-        let ret = if let Stmt::Block(_) = &*stmt.body {
-            true
-        } else {
-            false
-        };
-        Ok(ret)
+        self.write(")")?;
+        self.write_loop_body(&stmt.body)
     }
     /// Attempts to write a for of loop
     /// ```js
     /// for (let x of []) {
     ///
     /// }
+    /// for await (let x of []) {
+    ///
+    /// }
     /// ```
     pub fn write_for_of_stmt(&mut self, stmt: &ForOfStmt) -> Result<bool, IoError> {
         trace!("write_for_of_stmt");
-        self.write("for (")?;
+        if stmt.is_await {
+            self.write("for await (")?;
+        } else {
+            self.write("for (")?;
+        }
         self.write_loop_left(&stmt.left)?;
         self.write(" of ")?;
-        self.write_expr(&stmt.right)?;
-        self.write(") ")?;
-        self.write_stmt(&stmt.body)?;
-        let ret = if let Stmt::Block(_) = &*stmt.body {
+        self.write_single_expr(&stmt.right)?;
+        self.write(")")?;
+        self.write_loop_body(&stmt.body)
+    }
+    /// Attempts to write the body of a loop, an empty body
+    /// is written as a single `;` so it isn't lost
+    /// ```js
+    /// for (let x of []);
+    /// ```
+    /// The returned `bool` indicates if the caller should write
+    /// a new line after the loop
+    pub fn write_loop_body(&mut self, body: &Stmt) -> Result<bool, IoError> {
+        trace!("write_loop_body");
+        if let Stmt::Empty = body {
+            self.write_empty_stmt()?;
+            return Ok(true);
+        }
+        self.write_space_before_stmt(body)?;
+        self.write_stmt(body)?;
+        let ret = if let Stmt::Block(_) = body {
             true
         } else {
             false
@@ -968,7 +2008,7 @@ impl<T: Write> Writer<T> {
             self.write("{}")?;
             return Ok(());
         }
-        self.write_open_brace()?;
+        self.write_inline_open_brace()?;
         let mut after_first = false;
         for ref part in obj {
             if after_first {
@@ -1013,9 +2053,14 @@ impl<T: Write> Writer<T> {
             self.write(": ")?;
             self.write_property_value(&prop.value)?;
         } else {
-            match &prop.value {
-                PropertyValue::None => (),
-                PropertyValue::Expr(_) | PropertyValue::Pat(_) => {
+            match (&prop.key, &prop.value) {
+                (_, PropertyValue::None) => (),
+                // `{x}` written with the value duplicated, not a default of `x = x`
+                (
+                    PropertyKey::Expr(Expr::Ident(ref key)),
+                    PropertyValue::Expr(Expr::Ident(ref value)),
+                ) if key == value => (),
+                (_, PropertyValue::Expr(_)) | (_, PropertyValue::Pat(_)) => {
                     self.write(" = ")?;
                     self.write_property_value(&prop.value)?;
                 }
@@ -1081,6 +2126,18 @@ impl<T: Write> Writer<T> {
     /// ```
     pub fn write_function_args(&mut self, args: &[FunctionArg]) -> Res {
         trace!("write_function_args");
+        if args.len() > self.multi_line_list_threshold {
+            // nothing can follow a rest parameter, not even a comma
+            let trailing_comma = self.trailing_commas == TrailingCommaPolicy::All
+                && match args.last() {
+                    Some(FunctionArg::Pat(Pat::RestElement(_)))
+                    | Some(FunctionArg::Expr(Expr::Spread(_))) => false,
+                    _ => true,
+                };
+            return self.write_multi_line_list("(", ")", args, trailing_comma, |w, arg| {
+                w.write_function_arg(arg)
+            });
+        }
         self.write("(")?;
         let mut after_first = false;
         for ref arg in args {
@@ -1106,7 +2163,9 @@ impl<T: Write> Writer<T> {
     /// Write the block statement that makes up a function's body
     pub fn write_function_body(&mut self, body: &FunctionBody) -> Res {
         trace!("write_function_body");
+        self.context.push(WriterContext::FunctionBody);
         if body.len() == 0 {
+            self.write_brace_line_break()?;
             self.write("{ ")?;
         } else {
             self.write_open_brace()?;
@@ -1120,6 +2179,7 @@ impl<T: Write> Writer<T> {
         } else {
             self.write_close_brace()?;
         }
+        self.context.pop();
         Ok(())
     }
     /// Write a property that is a constructor for a class
@@ -1142,15 +2202,31 @@ impl<T: Write> Writer<T> {
     }
     /// Write a property key, taking into account of it should be wrapped in [] for "computed"
     /// properties
+    ///
+    /// resast has no private identifier, a private name like `#field`
+    /// needs to be stored with its `#` as part of an `Expr::Ident` key
     pub fn write_property_key(&mut self, key: &PropertyKey, computed: bool) -> Res {
         trace!("write_property_key");
         if computed {
             self.write("[")?;
         }
         match key {
-            PropertyKey::Expr(ref e) => self.write_expr(e)?,
+            PropertyKey::Expr(ref e) => self.write_single_expr(e)?,
             PropertyKey::Literal(ref l) => self.write_literal(l)?,
-            PropertyKey::Pat(ref p) => self.write_pattern(p)?,
+            PropertyKey::Pat(ref p) => {
+                // RESSA doesn't produce this variant, even computed keys
+                // in a destructuring pattern are `PropertyKey::Expr`, so
+                // anything other than an identifier is a malformed AST
+                debug_assert!(
+                    if let Pat::Identifier(_) = p {
+                        true
+                    } else {
+                        false
+                    },
+                    "only an identifier pattern can be a property key"
+                );
+                self.write_pattern(p)?
+            }
         }
         if computed {
             self.write("]")?;
@@ -1161,19 +2237,25 @@ impl<T: Write> Writer<T> {
     pub fn write_property_value(&mut self, value: &PropertyValue) -> Res {
         trace!("write_property_value");
         match value {
-            PropertyValue::Expr(ref e) => self.write_expr(e)?,
+            PropertyValue::Expr(ref e) => self.write_single_expr(e)?,
             PropertyValue::Pat(ref p) => self.write_pattern(p)?,
             PropertyValue::None => (),
         }
         Ok(())
     }
-    /// Writes a rest pattern
+    /// Writes the rest part of an object pattern
     /// ```js
-    /// let x = [...y];
+    /// let {a, ...rest} = obj;
     /// ```
+    /// The parser wraps this in a `Pat::RestElement` which writes the `...`
+    /// itself, anything else will have the `...` added here
     pub fn write_rest_pattern_part(&mut self, pat: &Pat) -> Res {
         trace!("write_rest_pattern_part");
-        self.write_pattern(pat)?;
+        if let Pat::RestElement(_) = pat {
+            self.write_pattern(pat)?;
+        } else {
+            self.write_rest_element(pat)?;
+        }
         Ok(())
     }
     /// Writes an array literal from a pattern
@@ -1217,7 +2299,7 @@ impl<T: Write> Writer<T> {
         trace!("write_assignment_pattern");
         self.write_pattern(&assignment.left)?;
         self.write(" = ")?;
-        self.write_expr(&assignment.right)?;
+        self.write_single_expr(&assignment.right)?;
         Ok(())
     }
 
@@ -1230,43 +2312,27 @@ impl<T: Write> Writer<T> {
 
     pub fn write_expr(&mut self, expr: &Expr) -> Res {
         trace!("write_expr");
-        let cached_state = self.at_top_level;
         match expr {
             Expr::Literal(ref expr) => self.write_literal(expr)?,
             Expr::This => self.write_this_expr()?,
             Expr::Super => self.write_super_expr()?,
             Expr::Array(ref expr) => self.write_array_expr(expr)?,
             Expr::Object(ref expr) => self.write_object_expr(expr)?,
-            Expr::Function(ref expr) => {
-                self.at_top_level = false;
-                self.write_function(expr)?;
-                self.at_top_level = cached_state;
-            }
+            Expr::Function(ref expr) => self.write_function(expr)?,
             Expr::Unary(ref expr) => self.write_unary_expr(expr)?,
             Expr::Update(ref expr) => self.write_update_expr(expr)?,
             Expr::Binary(ref expr) => self.write_binary_expr(expr)?,
-            Expr::Assignment(ref expr) => {
-                self.at_top_level = false;
-                self.write_assignment_expr(expr)?
-            }
+            Expr::Assignment(ref expr) => self.write_assignment_expr(expr)?,
             Expr::Logical(ref expr) => self.write_logical_expr(expr)?,
             Expr::Member(ref expr) => self.write_member_expr(expr)?,
             Expr::Conditional(ref expr) => self.write_conditional_expr(expr)?,
             Expr::Call(ref expr) => self.write_call_expr(expr)?,
             Expr::New(ref expr) => self.write_new_expr(expr)?,
-            Expr::Sequence(ref expr) => self.write_sequence_expr(expr)?,
+            Expr::Sequence(ref expr) => self.write_sequence_expr_inner(expr)?,
             Expr::Spread(ref expr) => self.write_spread_expr(expr)?,
-            Expr::ArrowFunction(ref expr) => {
-                self.at_top_level = false;
-                self.write_arrow_function_expr(expr)?;
-                self.at_top_level = cached_state;
-            }
+            Expr::ArrowFunction(ref expr) => self.write_arrow_function_expr(expr)?,
             Expr::Yield(ref expr) => self.write_yield_expr(expr)?,
-            Expr::Class(ref expr) => {
-                self.at_top_level = false;
-                self.write_class(expr)?;
-                self.at_top_level = cached_state;
-            }
+            Expr::Class(ref expr) => self.write_class(expr)?,
             Expr::MetaProperty(ref expr) => self.write_meta_property(expr)?,
             Expr::Await(ref expr) => self.write_await_expr(expr)?,
             Expr::Ident(ref expr) => self.write_ident(expr)?,
@@ -1297,11 +2363,20 @@ impl<T: Write> Writer<T> {
             self.write("[]")?;
             return Ok(());
         }
+        if arr.len() > self.multi_line_list_threshold {
+            // a trailing hole is only kept by the comma after it
+            let trailing_comma = self.trailing_commas != TrailingCommaPolicy::Never
+                || arr.last().map(|e| e.is_none()).unwrap_or(false);
+            return self.write_multi_line_list("[", "]", arr, trailing_comma, |w, e| match e {
+                Some(ref e) => w.write_single_expr(e),
+                None => Ok(()),
+            });
+        }
         self.write("[")?;
         let last_idx = arr.len() - 1;
         for (i, ref e) in arr.iter().enumerate() {
             if let Some(ref e) = e {
-                self.write_expr(e)?;
+                self.write_single_expr(e)?;
                 if i < last_idx {
                     self.write(", ")?;
                 }
@@ -1325,6 +2400,13 @@ impl<T: Write> Writer<T> {
             self.write("{}")?;
             return Ok(());
         }
+        let prop_count = obj
+            .iter()
+            .filter(|p| matches!(p, ObjectProperty::Property(_)))
+            .count();
+        if prop_count > self.multi_line_object_threshold {
+            return self.write_object_expr_multiline(obj);
+        }
         self.write("{")?;
         let mut after_first = false;
         for ref prop in obj {
@@ -1341,8 +2423,58 @@ impl<T: Write> Writer<T> {
         self.write("}")?;
         Ok(())
     }
+    /// Write an object literal with each property
+    /// and spread on its own line
+    fn write_object_expr_multiline(&mut self, obj: &ObjectExpr) -> Res {
+        trace!("write_object_expr_multiline");
+        let trailing_comma = self.trailing_commas != TrailingCommaPolicy::Never;
+        self.write_multi_line_list("{", "}", obj, trailing_comma, |w, prop| match prop {
+            ObjectProperty::Property(ref p) => w.write_property(p),
+            ObjectProperty::Spread(ref e) => w.write_expr(e),
+        })
+    }
+    /// Write a list between `open` and `close` with each item on its own
+    /// line, `trailing_comma` adds a comma after the last item
+    fn write_multi_line_list<I, F>(
+        &mut self,
+        open: &str,
+        close: &str,
+        items: &[I],
+        trailing_comma: bool,
+        mut write_item: F,
+    ) -> Res
+    where
+        F: FnMut(&mut Self, &I) -> Res,
+    {
+        self.write(open)?;
+        self.current_indent += 1;
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                self.write(",")?;
+            }
+            self.write_new_line()?;
+            self.write_leading_whitespace()?;
+            write_item(self, item)?;
+        }
+        if trailing_comma {
+            self.write(",")?;
+        }
+        self.write_new_line()?;
+        self.current_indent = self.current_indent.saturating_sub(1);
+        self.write_leading_whitespace()?;
+        self.write(close)
+    }
     /// Write a function. This is used to write the contents of both a `Declaration::Function`
     /// and an `Expr::Function`
+    ///
+    /// This never writes a semicolon after the closing brace, a declaration doesn't need one
+    /// and for an expression it is up to the enclosing statement to terminate it
+    /// ```js
+    /// function thing() {
+    /// }
+    /// let stuff = function() {
+    /// };
+    /// ```
     pub fn write_function(&mut self, func: &Function) -> Res {
         trace!("write_function");
         if func.is_async {
@@ -1350,16 +2482,20 @@ impl<T: Write> Writer<T> {
         }
         self.write("function")?;
         if let Some(ref id) = func.id {
-            self.write(" ")?;
             if func.generator {
-                self.write("*")?;
+                match self.generator_star_spacing {
+                    GeneratorStarSpacing::AfterFunction => self.write("* ")?,
+                    GeneratorStarSpacing::BeforeName => self.write(" *")?,
+                }
+            } else {
+                self.write(" ")?;
             }
             self.write(id)?;
         } else if func.generator {
             self.write("*")?;
         }
         self.write_function_args(&func.params)?;
-        self.write(" ")?;
+        self.write_space_before_brace()?;
         self.write_function_body(&func.body)
     }
     /// Write a unary expression
@@ -1383,7 +2519,8 @@ impl<T: Write> Writer<T> {
             | Expr::Logical(_)
             | Expr::Conditional(_)
             | Expr::ArrowFunction(_)
-            | Expr::Function(_) => self.write_wrapped_expr(&unary.argument)?,
+            | Expr::Function(_)
+            | Expr::Sequence(_) => self.write_wrapped_expr(&unary.argument)?,
             Expr::Unary(_) | Expr::Update(_) => {
                 self.write(" ")?;
                 self.write_expr(&unary.argument)?;
@@ -1443,21 +2580,66 @@ impl<T: Write> Writer<T> {
     /// ```
     pub fn write_binary_expr(&mut self, binary: &BinaryExpr) -> Res {
         trace!("write_binary_expr {:#?}", binary);
-        let wrap = self.in_for_init && binary.operator == BinaryOperator::In;
+        let wrap = self.in_context(WriterContext::ForInit) && binary.operator == BinaryOperator::In;
         if wrap {
             self.write("(")?;
         }
-        self.write_binary_side(&*binary.left)?;
-        self.write(" ")?;
+        match (&binary.operator, &*binary.left) {
+            // `-1 ** 2` is a syntax error, any unary
+            // operand on the left of `**` has to be wrapped
+            (BinaryOperator::PowerOf, Expr::Unary(_)) => self.write_wrapped_expr(&binary.left)?,
+            (op, Expr::Binary(ref inner)) if !binary_needs_wrap(op, &inner.operator, false) => {
+                self.write_expr(&binary.left)?
+            }
+            _ => self.write_binary_side(&*binary.left)?,
+        }
+        // keyword operators always need to be separated from their operands
+        let space = !self.minify
+            || binary.operator == BinaryOperator::In
+            || binary.operator == BinaryOperator::InstanceOf;
+        // `a-- > b` would start an html comment with `-->`
+        let space_before = match binary.operator {
+            BinaryOperator::GreaterThan
+            | BinaryOperator::GreaterThanEqual
+            | BinaryOperator::RightShift
+            | BinaryOperator::UnsignedRightShift => Self::ends_with_decrement(&binary.left),
+            _ => false,
+        };
+        if space || space_before {
+            self.write(" ")?;
+        }
         self.write_binary_operator(&binary.operator)?;
-        self.write(" ")?;
-        self.write_binary_side(&*binary.right)?;
+        // `a - -b` would become `a--b` and
+        // `a < !--b` would start an html comment with `<!--`
+        let space_after = match binary.operator {
+            BinaryOperator::LessThan | BinaryOperator::LeftShift => {
+                Self::starts_with_not(&binary.right)
+            }
+            _ => false,
+        };
+        if space || space_after || Self::starts_with_sign(&binary.right) {
+            self.write(" ")?;
+        }
+        match (&binary.operator, &*binary.right) {
+            // `a / /re/` is easily misread as a comment or
+            // another division so the regex is always wrapped
+            (BinaryOperator::Over, Expr::Literal(Literal::RegEx(_))) => {
+                self.write_wrapped_expr(&binary.right)?
+            }
+            (op, Expr::Binary(ref inner)) if !binary_needs_wrap(op, &inner.operator, true) => {
+                self.write_expr(&binary.right)?
+            }
+            _ => self.write_binary_side(&*binary.right)?,
+        }
         if wrap {
             self.write(")")?;
         }
         Ok(())
     }
 
+    /// Write one side of a binary expression, wrapping anything that isn't a
+    /// primary expression. `write_binary_expr` checks `binary_precedence`
+    /// first so a nested binary expression is only wrapped when needed
     pub fn write_binary_side(&mut self, side: &Expr) -> Res {
         match &*side {
             Expr::Assignment(_)
@@ -1465,7 +2647,8 @@ impl<T: Write> Writer<T> {
             | Expr::Logical(_)
             | Expr::Function(_)
             | Expr::ArrowFunction(_)
-            | Expr::Binary(_) => self.write_wrapped_expr(side),
+            | Expr::Binary(_)
+            | Expr::Sequence(_) => self.write_wrapped_expr(side),
             _ => self.write_expr(side),
         }
     }
@@ -1506,17 +2689,7 @@ impl<T: Write> Writer<T> {
     /// ```
     pub fn write_assignment_expr(&mut self, assignment: &AssignmentExpr) -> Res {
         trace!("write_assignment_expr");
-        let wrap_self = match &assignment.left {
-            AssignmentLeft::Expr(ref e) => match &**e {
-                Expr::Object(_) | Expr::Array(_) => true,
-                _ => false,
-            },
-            AssignmentLeft::Pat(ref p) => match p {
-                Pat::Array(_) => true,
-                Pat::Object(_) => true,
-                _ => false,
-            },
-        };
+        let wrap_self = Self::assignment_left_needs_wrap(&assignment.left);
         if wrap_self {
             self.write("(")?;
         }
@@ -1524,16 +2697,32 @@ impl<T: Write> Writer<T> {
             AssignmentLeft::Expr(ref e) => self.write_expr(e)?,
             AssignmentLeft::Pat(ref p) => self.write_pattern(p)?,
         }
-        self.write(" ")?;
+        self.write_operator_space()?;
         self.write_assignment_operator(&assignment.operator)?;
-        self.write(" ")?;
-        self.write_expr(&assignment.right)?;
+        self.write_operator_space()?;
+        self.write_single_expr(&assignment.right)?;
         if wrap_self {
             self.write(")")?;
         }
         Ok(())
     }
 
+    /// Check if an assignment needs to be wrapped in parentheses because
+    /// its left side is an object or array, a destructuring assignment
+    /// starting with `{` would be read as a block
+    fn assignment_left_needs_wrap(left: &AssignmentLeft) -> bool {
+        match left {
+            AssignmentLeft::Expr(ref e) => match &**e {
+                Expr::Object(_) | Expr::Array(_) => true,
+                _ => false,
+            },
+            AssignmentLeft::Pat(ref p) => match p {
+                Pat::Array(_) | Pat::Object(_) => true,
+                _ => false,
+            },
+        }
+    }
+
     pub fn write_assignment_operator(&mut self, op: &AssignmentOperator) -> Res {
         let s = match op {
             AssignmentOperator::AndEqual => "&=",
@@ -1560,9 +2749,10 @@ impl<T: Write> Writer<T> {
     /// ```
     pub fn write_logical_expr(&mut self, logical: &LogicalExpr) -> Res {
         trace!("write_logical_expr {:#?}", logical);
+        let prec = logical_precedence(&logical.operator);
         let wrap_left = match &*logical.left {
-            Expr::Logical(ref l) => l.operator == LogicalOperator::Or,
-            Expr::Assignment(_) | Expr::Conditional(_) => true,
+            Expr::Logical(ref l) => logical_precedence(&l.operator) < prec,
+            Expr::Assignment(_) | Expr::Conditional(_) | Expr::Sequence(_) => true,
             _ => false,
         };
         if wrap_left {
@@ -1570,14 +2760,16 @@ impl<T: Write> Writer<T> {
         } else {
             self.write_expr(&logical.left)?;
         }
-        self.write(" ")?;
+        self.write_operator_space()?;
         self.write_logical_operator(&logical.operator)?;
+        // both operators are left associative so an equal
+        // precedence on the right still needs to be wrapped
         let wrap_right = match &*logical.right {
-            Expr::Logical(ref _l) => true,
-            Expr::Assignment(_) | Expr::Conditional(_) => true,
+            Expr::Logical(ref r) => logical_precedence(&r.operator) <= prec,
+            Expr::Assignment(_) | Expr::Conditional(_) | Expr::Sequence(_) => true,
             _ => false,
         };
-        self.write(" ")?;
+        self.write_operator_space()?;
         if wrap_right {
             self.write_wrapped_expr(&logical.right)?;
         } else {
@@ -1586,6 +2778,44 @@ impl<T: Write> Writer<T> {
         Ok(())
     }
 
+    /// Writes a nullish coalescing expression, either side
+    /// will be wrapped in parentheses when it is an `&&` or `||`
+    /// expression since mixing them without parentheses is a syntax error
+    /// ```js
+    /// a ?? b
+    /// (a || b) ?? c
+    /// a ?? (b && c)
+    /// ```
+    pub fn write_nullish_coalescing(&mut self, left: &Expr, right: &Expr) -> Res {
+        trace!("write_nullish_coalescing");
+        let wrap = |e: &Expr| match e {
+            Expr::Logical(_)
+            | Expr::Assignment(_)
+            | Expr::Conditional(_)
+            | Expr::Sequence(_)
+            | Expr::Yield(_) => true,
+            _ => false,
+        };
+        if wrap(left) {
+            self.write_wrapped_expr(left)?;
+        } else {
+            self.write_expr(left)?;
+        }
+        self.write_operator_space()?;
+        self.write("??")?;
+        self.write_operator_space()?;
+        if wrap(right) {
+            self.write_wrapped_expr(right)?;
+        } else {
+            self.write_expr(right)?;
+        }
+        Ok(())
+    }
+    /// Writes a logical operator
+    ///
+    /// resast's `LogicalOperator` only has `&&` and `||`,
+    /// `??` is written with `write_nullish_coalescing` which
+    /// also takes care of the parentheses it requires
     pub fn write_logical_operator(&mut self, op: &LogicalOperator) -> Res {
         trace!("write_logical_operator");
         let s = match op {
@@ -1602,7 +2832,81 @@ impl<T: Write> Writer<T> {
     /// ```
     pub fn write_member_expr(&mut self, member: &MemberExpr) -> Res {
         trace!("write_member_expr");
-        match &*member.object {
+        self.write_member_object(&member.object)?;
+        if member.computed {
+            self.write("[")?;
+        } else {
+            self.write(".")?;
+        }
+        self.write_single_expr(&member.property)?;
+        if member.computed {
+            self.write("]")?;
+        }
+        Ok(())
+    }
+    /// Writes an optional member expression
+    /// ```js
+    /// console?.log
+    /// console?.['log']
+    /// ```
+    /// resast has no optional member node, so `obj` is always written
+    /// as a regular expression. Longer chains like `a?.b?.c` are written
+    /// by following this with `write_optional_chain_link`
+    pub fn write_optional_chain_member(&mut self, obj: &Expr, prop: &Expr, computed: bool) -> Res {
+        trace!("write_optional_chain_member");
+        self.write_member_object(obj)?;
+        self.write_optional_chain_link(prop, computed)
+    }
+    /// Writes only the `?.prop` part of an optional member expression,
+    /// continuing whatever was written last
+    /// ```js
+    /// ?.c
+    /// ?.[key]
+    /// ```
+    pub fn write_optional_chain_link(&mut self, prop: &Expr, computed: bool) -> Res {
+        trace!("write_optional_chain_link");
+        if computed {
+            self.write("?.[")?;
+        } else {
+            self.write("?.")?;
+        }
+        self.write_single_expr(prop)?;
+        if computed {
+            self.write("]")?;
+        }
+        Ok(())
+    }
+    /// Writes an optional call expression, resast doesn't have an
+    /// `optional` flag on `CallExpr` so this has to be called directly
+    /// ```js
+    /// callback?.(a, b)
+    /// ```
+    pub fn write_optional_chain_call(&mut self, callee: &Expr, arguments: &[Expr]) -> Res {
+        trace!("write_optional_chain_call");
+        self.write_member_object(callee)?;
+        self.write("?.")?;
+        self.write_paren_list(arguments)?;
+        Ok(())
+    }
+    /// Writes a dynamic import, resast doesn't have an `Expr` variant
+    /// for this so it has to be called directly
+    /// ```js
+    /// import('./module.js')
+    /// ```
+    pub fn write_import_expr(&mut self, arg: &Expr) -> Res {
+        trace!("write_import_expr");
+        self.write("import(")?;
+        self.write_single_expr(arg)?;
+        self.write(")")?;
+        Ok(())
+    }
+    /// Writes the object of a member expression, wrapping it
+    /// in parentheses when needed
+    ///
+    /// `Expr::New` is never wrapped, the arguments are always written
+    /// so `(new Foo).bar` is written as the equivalent `new Foo().bar`
+    fn write_member_object(&mut self, obj: &Expr) -> Res {
+        match obj {
             Expr::Assignment(_)
             | Expr::Literal(Literal::Number(_))
             | Expr::Conditional(_)
@@ -1612,19 +2916,10 @@ impl<T: Write> Writer<T> {
             | Expr::Object(_)
             | Expr::Binary(_)
             | Expr::Unary(_)
-            | Expr::Update(_) => self.write_wrapped_expr(&member.object)?,
-            _ => self.write_expr(&member.object)?,
-        }
-        if member.computed {
-            self.write("[")?;
-        } else {
-            self.write(".")?;
-        }
-        self.write_expr(&member.property)?;
-        if member.computed {
-            self.write("]")?;
+            | Expr::Update(_)
+            | Expr::Sequence(_) => self.write_wrapped_expr(obj),
+            _ => self.write_expr(obj),
         }
-        Ok(())
     }
     /// Writes a conditional expression
     /// ```js
@@ -1632,15 +2927,32 @@ impl<T: Write> Writer<T> {
     /// ```
     pub fn write_conditional_expr(&mut self, conditional: &ConditionalExpr) -> Res {
         trace!("write_conditional_expr");
-        self.write_expr(&conditional.test)?;
-        self.write(" ? ")?;
+        // anything with a lower precedence than `? :` would
+        // otherwise take the conditional as part of itself
+        match &*conditional.test {
+            Expr::Conditional(_)
+            | Expr::Assignment(_)
+            | Expr::ArrowFunction(_)
+            | Expr::Yield(_) => self.write_wrapped_expr(&conditional.test)?,
+            _ => self.write_single_expr(&conditional.test)?,
+        }
+        self.write_operator_space()?;
+        self.write("?")?;
+        self.write_operator_space()?;
         if let Expr::Logical(_) = &*conditional.consequent {
             self.write_wrapped_expr(&conditional.consequent)?;
         } else {
-            self.write_expr(&conditional.consequent)?;
+            self.write_single_expr(&conditional.consequent)?;
+        }
+        self.write_operator_space()?;
+        self.write(":")?;
+        self.write_operator_space()?;
+        match &*conditional.alternate {
+            Expr::Conditional(_) if self.explicit_nested_ternary => {
+                self.write_wrapped_expr(&conditional.alternate)?
+            }
+            _ => self.write_single_expr(&conditional.alternate)?,
         }
-        self.write(" : ")?;
-        self.write_expr(&conditional.alternate)?;
         Ok(())
     }
     /// Writes a call expression
@@ -1653,9 +2965,9 @@ impl<T: Write> Writer<T> {
         trace!("write_call_expr");
         match &*call.callee {
             Expr::Function(_) | Expr::ArrowFunction(_) => self.write_wrapped_expr(&call.callee)?,
-            _ => self.write_expr(&call.callee)?,
+            _ => self.write_single_expr(&call.callee)?,
         }
-        self.write_sequence_expr(&call.arguments)?;
+        self.write_paren_list(&call.arguments)?;
         Ok(())
     }
     /// Writes a new expression
@@ -1664,41 +2976,89 @@ impl<T: Write> Writer<T> {
     /// ```
     pub fn write_new_expr(&mut self, new: &NewExpr) -> Res {
         trace!("write_new_expr");
+        if let Expr::Ident(ref id) = &*new.callee {
+            if id == "target" && new.arguments.is_empty() {
+                warn!(
+                    "`new target()` written for a NewExpr, `new.target` should be a MetaProperty"
+                );
+            }
+        }
         self.write("new ")?;
         match &*new.callee {
             Expr::Assignment(_) | Expr::Call(_) => self.write_wrapped_expr(&new.callee)?,
-            _ => self.write_expr(&new.callee)?,
+            _ => self.write_single_expr(&new.callee)?,
         }
-        self.write_sequence_expr(&new.arguments)?;
+        self.write_paren_list(&new.arguments)?;
         Ok(())
     }
-    /// Writes a sequence of sub-expressions
+    /// Writes a parenthesized, comma separated list of expressions,
+    /// this is used for the arguments of call and new expressions
     /// ```js
-    /// a = b, c = d, q * 100
+    /// (a, b = c, ...d)
     /// ```
-    pub fn write_sequence_expr(&mut self, sequence: &[Expr]) -> Res {
-        trace!("write_sequence_expr");
+    pub fn write_paren_list(&mut self, list: &[Expr]) -> Res {
+        trace!("write_paren_list");
+        if list.len() > self.multi_line_list_threshold {
+            let trailing_comma = self.trailing_commas == TrailingCommaPolicy::All;
+            return self.write_multi_line_list("(", ")", list, trailing_comma, |w, e| {
+                w.write_single_expr(e)
+            });
+        }
         let mut after_first = false;
         self.write("(")?;
-        for ref e in sequence {
+        for ref e in list {
             if after_first {
                 self.write(", ")?;
             }
-            self.write_expr(e)?;
+            self.write_single_expr(e)?;
             after_first = true;
         }
         self.write(")")?;
         Ok(())
     }
-    /// Writes a spread expression
+    /// Writes the contents of a sequence expression without any
+    /// parentheses, anywhere a comma would be ambiguous the
+    /// caller is responsible for wrapping it
+    /// ```js
+    /// a = b, c = d, q * 100
+    /// ```
+    pub fn write_sequence_expr_inner(&mut self, sequence: &[Expr]) -> Res {
+        trace!("write_sequence_expr_inner");
+        let mut after_first = false;
+        for ref e in sequence {
+            if after_first {
+                self.write(", ")?;
+            }
+            self.write_single_expr(e)?;
+            after_first = true;
+        }
+        Ok(())
+    }
+    /// Old name of `write_paren_list`
+    #[deprecated(since = "0.2.3", note = "Use write_paren_list instead")]
+    pub fn write_sequence_expr(&mut self, sequence: &[Expr]) -> Res {
+        self.write_paren_list(sequence)
+    }
+    /// Write an expression where only a single expression is allowed,
+    /// a sequence expression will be wrapped in parentheses
+    fn write_single_expr(&mut self, expr: &Expr) -> Res {
+        if let Expr::Sequence(_) = expr {
+            self.write_wrapped_expr(expr)
+        } else {
+            self.write_expr(expr)
+        }
+    }
+    /// Writes a spread expression, this is used for spread elements in
+    /// array literals and call arguments
     /// ```js
     /// function(...args) {
     /// }
+    /// thing(a, ...b, c);
     /// ```
     pub fn write_spread_expr(&mut self, spread: &Expr) -> Res {
         trace!("write_spread_expr");
         self.write("...")?;
-        self.write_expr(spread)?;
+        self.write_single_expr(spread)?;
         Ok(())
     }
     /// Writes and arrow function
@@ -1727,16 +3087,50 @@ impl<T: Write> Writer<T> {
         } else {
             self.write_function_args(&func.params)?;
         }
-        self.write(" => ")?;
+        self.write_operator_space()?;
+        self.write("=>")?;
         match &func.body {
-            ArrowFunctionBody::FunctionBody(ref b) => self.write_function_body(b)?,
-            ArrowFunctionBody::Expr(ref e) => match &**e {
-                Expr::Object(_) | Expr::Binary(_) => self.write_wrapped_expr(e)?,
-                _ => self.write_expr(e)?,
-            },
-        }
-        Ok(())
-    }
+            ArrowFunctionBody::FunctionBody(ref b) => {
+                if self.arrow_expression_body {
+                    if let Some(e) = Self::arrow_body_return(b) {
+                        self.write_operator_space()?;
+                        return self.write_arrow_expr_body(e);
+                    }
+                }
+                self.write_space_before_brace()?;
+                self.write_function_body(b)?
+            }
+            ArrowFunctionBody::Expr(ref e) => {
+                self.write_operator_space()?;
+                self.write_arrow_expr_body(e)?
+            }
+        }
+        Ok(())
+    }
+    /// Writes the expression body of an arrow function, only wrapping
+    /// it when it would otherwise be read as a block or as more arguments
+    fn write_arrow_expr_body(&mut self, e: &Expr) -> Res {
+        match e {
+            Expr::Object(_) | Expr::Sequence(_) => self.write_wrapped_expr(e),
+            Expr::Binary(_) if Self::starts_with_brace_or_function(e) => self.write_wrapped_expr(e),
+            _ => self.write_expr(e),
+        }
+    }
+    /// Get the returned expression of an arrow function's body
+    /// if that body is only a single `return` of something other
+    /// than `undefined`
+    fn arrow_body_return(body: &FunctionBody) -> Option<&Expr> {
+        if body.len() != 1 {
+            return None;
+        }
+        match &body[0] {
+            ProgramPart::Stmt(Stmt::Return(Some(e))) => match e {
+                Expr::Ident(i) if i == "undefined" => None,
+                _ => Some(e),
+            },
+            _ => None,
+        }
+    }
     /// Writes a yield expression
     /// ```js
     /// function *gen() {
@@ -1755,7 +3149,7 @@ impl<T: Write> Writer<T> {
             self.write("*")?;
         }
         if let Some(ref arg) = &expr.argument {
-            self.write_expr(arg)?;
+            self.write_single_expr(arg)?;
         }
         Ok(())
     }
@@ -1769,6 +3163,8 @@ impl<T: Write> Writer<T> {
     ///     }
     /// }
     /// ```
+    /// Both sides are written as plain text so `import.meta`
+    /// is produced the same way
     pub fn write_meta_property(&mut self, meta: &MetaProperty) -> Res {
         trace!("write_meta_property");
         self.write_ident(&meta.meta)?;
@@ -1780,7 +3176,7 @@ impl<T: Write> Writer<T> {
     pub fn write_await_expr(&mut self, expr: &Expr) -> Res {
         trace!("write_await_expr");
         self.write("await ")?;
-        self.write_expr(expr)?;
+        self.write_single_expr(expr)?;
         Ok(())
     }
     /// Write a plain identifier
@@ -1794,7 +3190,15 @@ impl<T: Write> Writer<T> {
     /// ```
     pub fn write_tagged_template(&mut self, template: &TaggedTemplateExpr) -> Res {
         trace!("write_tagged_template");
-        self.write_expr(&template.tag)?;
+        match &*template.tag {
+            Expr::Conditional(_)
+            | Expr::Assignment(_)
+            | Expr::Sequence(_)
+            | Expr::Logical(_)
+            | Expr::Binary(_)
+            | Expr::ArrowFunction(_) => self.write_wrapped_expr(&template.tag)?,
+            _ => self.write_expr(&template.tag)?,
+        }
         self.write_template(&template.quasi)?;
         Ok(())
     }
@@ -1811,6 +3215,13 @@ impl<T: Write> Writer<T> {
     /// true,
     /// /.+/g
     /// `things`
+    /// ```
+    /// A `Literal::Template` is routed to `write_template` so `write_expr`
+    /// doesn't need a special case for template literals
+    ///
+    /// A `Literal::Number` is written exactly as it is stored, resast
+    /// has no BigInt variant so the `n` suffix of `42n` is kept as part
+    /// of the number's text, the same goes for separators like `1_000`
     pub fn write_literal(&mut self, lit: &Literal) -> Res {
         trace!("write_literal");
         match lit {
@@ -1844,10 +3255,17 @@ impl<T: Write> Writer<T> {
 
     fn re_write_string(&mut self, s: &str, c: char) -> Res {
         let s = rewrite::re_write(s, c).unwrap_or(s.to_string());
-        self.write(&s)?;;
+        self.write(&s)?;
         Ok(())
     }
 
+    /// Write a regular expression literal, the flags are written
+    /// as is so newer flags like `d` and `v` are preserved
+    /// ```js
+    /// /.+/gd
+    /// ```
+    /// A regex used as a divisor is wrapped in parentheses by
+    /// `write_binary_expr`, anywhere else it is written as is
     pub fn write_regex(&mut self, regex: &RegEx) -> Res {
         trace!("write_regex");
         self.write("/")?;
@@ -1857,16 +3275,26 @@ impl<T: Write> Writer<T> {
         Ok(())
     }
 
+    /// Write a template literal, the backticks and the `${`/`}` around
+    /// each expression are written here so the `cooked` text of each element
+    /// is used (the `raw` text would include those delimiters)
+    /// ```js
+    /// `things ${0} stuff`
+    /// ```
     pub fn write_template(&mut self, template: &TemplateLiteral) -> Res {
         trace!("write_template");
+        self.write("`")?;
         let mut quasis = template.quasis.iter();
         let mut exprs = template.expressions.iter();
         while let Some(quasi) = quasis.next() {
-            self.write(&quasi.raw)?;
+            self.write(&quasi.cooked)?;
             if let Some(exp) = exprs.next() {
+                self.write("${")?;
                 self.write_expr(exp)?;
+                self.write("}")?;
             }
         }
+        self.write("`")?;
         Ok(())
     }
 
@@ -1874,17 +3302,64 @@ impl<T: Write> Writer<T> {
         trace!("write_empty_stmt");
         self.write(";")
     }
+    /// Write the semicolon ending a statement, unless
+    /// the `SemicolonPolicy` says to leave it out
+    fn write_stmt_end(&mut self) -> Res {
+        if self.omit_semicolons() {
+            Ok(())
+        } else {
+            self.write_empty_stmt()
+        }
+    }
+    /// `SemicolonPolicy::Never` relies on a line break after each statement,
+    /// when `new_line` is empty (minified) the semicolons are still written
+    fn omit_semicolons(&self) -> bool {
+        self.semicolons == SemicolonPolicy::Never && !self.new_line.is_empty()
+    }
 
     pub fn write_open_brace(&mut self) -> Res {
         trace!("write_open_brace");
+        self.write_brace_line_break()?;
+        self.write_inline_open_brace()
+    }
+    /// Write an opening brace that stays on the current line
+    /// regardless of the `BraceStyle`
+    fn write_inline_open_brace(&mut self) -> Res {
         self.write("{")?;
         self.current_indent += 1;
         Ok(())
     }
+    /// Write the space between a keyword or parenthetical and the
+    /// opening brace that follows it, with `BraceStyle::NextLine`
+    /// the brace starts a new line instead so nothing is written
+    fn write_space_before_brace(&mut self) -> Res {
+        if self.brace_style == BraceStyle::NextLine {
+            return Ok(());
+        }
+        self.write(" ")
+    }
+    /// Write the space before the body of a statement like
+    /// `if` or `while`, only a block body can start a new line
+    fn write_space_before_stmt(&mut self, body: &Stmt) -> Res {
+        if let Stmt::Block(_) = body {
+            self.write_space_before_brace()
+        } else {
+            self.write(" ")
+        }
+    }
+    /// Start a new line at the current indentation
+    /// when using `BraceStyle::NextLine`
+    fn write_brace_line_break(&mut self) -> Res {
+        if self.brace_style == BraceStyle::NextLine {
+            self.write_new_line()?;
+            self.write_leading_whitespace()?;
+        }
+        Ok(())
+    }
 
     pub fn write_close_brace(&mut self) -> Res {
         trace!("write_close_brace");
-        self.current_indent -= 1;
+        self.current_indent = self.current_indent.saturating_sub(1);
         self.write_leading_whitespace()?;
         self.write("}")?;
         Ok(())
@@ -1903,7 +3378,17 @@ impl<T: Write> Writer<T> {
     }
 
     fn write(&mut self, s: &str) -> Res {
-        let _ = self.out.write(s.as_bytes())?;
+        self.out.write_all(s.as_bytes())?;
+        self.bytes_written += s.len();
+        // strings other than new lines can contain a line break,
+        // a template literal for example
+        match s.rfind('\n') {
+            Some(idx) => {
+                self.current_line += s.matches('\n').count();
+                self.current_column = s[idx + 1..].encode_utf16().count();
+            }
+            None => self.current_column += s.encode_utf16().count(),
+        }
         Ok(())
     }
     pub fn write_comment(&mut self, comment: Comment) -> Res {
@@ -1918,17 +3403,179 @@ impl<T: Write> Writer<T> {
         }
         Ok(())
     }
+    /// Write a comment on its own line at the current
+    /// indentation, before the next node is written
+    /// ```js
+    /// // comment
+    /// let x = 0;
+    /// ```
+    ///
+    /// A line comment always ends with a line break, even when
+    /// `new_line` is empty, so it can't swallow the next node
+    pub fn write_comment_before(&mut self, comment: Comment) -> Res {
+        trace!("write_comment_before");
+        let line_comment = comment.kind != CommentKind::Multi;
+        self.write_leading_whitespace()?;
+        self.write_comment(comment)?;
+        if line_comment && self.new_line.is_empty() {
+            self.write("\n")
+        } else {
+            self.write_new_line()
+        }
+    }
+    /// Write a comment at the end of the current line
+    /// ```js
+    /// let x = 0; // comment
+    /// ```
+    /// No line break is written after the comment, when writing
+    /// a line comment the caller is responsible for ending the line
+    pub fn write_comment_after(&mut self, comment: Comment) -> Res {
+        trace!("write_comment_after");
+        self.write(" ")?;
+        self.write_comment(comment)
+    }
+}
+
+/// The relative precedence of a binary operator, a higher
+/// number binds more tightly
+fn binary_precedence(op: &BinaryOperator) -> u8 {
+    match op {
+        BinaryOperator::PowerOf => 11,
+        BinaryOperator::Times | BinaryOperator::Over | BinaryOperator::Mod => 10,
+        BinaryOperator::Plus | BinaryOperator::Minus => 9,
+        BinaryOperator::LeftShift
+        | BinaryOperator::RightShift
+        | BinaryOperator::UnsignedRightShift => 8,
+        BinaryOperator::LessThan
+        | BinaryOperator::LessThanEqual
+        | BinaryOperator::GreaterThan
+        | BinaryOperator::GreaterThanEqual
+        | BinaryOperator::In
+        | BinaryOperator::InstanceOf => 7,
+        BinaryOperator::Equal
+        | BinaryOperator::NotEqual
+        | BinaryOperator::StrictEqual
+        | BinaryOperator::StrictNotEqual => 6,
+        BinaryOperator::And => 5,
+        BinaryOperator::XOr => 4,
+        BinaryOperator::Or => 3,
+    }
+}
+
+/// The relative precedence of a logical operator, on the same
+/// scale as `binary_precedence` so both are lower than any binary operator
+fn logical_precedence(op: &LogicalOperator) -> u8 {
+    match op {
+        LogicalOperator::And => 2,
+        LogicalOperator::Or => 1,
+    }
+}
+
+/// Check if a binary expression with the operator `inner` needs to be
+/// wrapped when it is an operand of a binary expression with the operator `outer`.
+///
+/// A lower precedence always needs parentheses, an equal precedence only
+/// needs them on the right since everything but `**` is left associative
+fn binary_needs_wrap(outer: &BinaryOperator, inner: &BinaryOperator, is_right: bool) -> bool {
+    let outer_prec = binary_precedence(outer);
+    let inner_prec = binary_precedence(inner);
+    if inner_prec != outer_prec {
+        return inner_prec < outer_prec;
+    }
+    if *outer == BinaryOperator::PowerOf {
+        !is_right
+    } else {
+        is_right
+    }
+}
+
+impl Writer<Vec<u8>> {
+    /// Consume the writer, converting everything written into a `String`
+    /// ```
+    /// use resast::prelude::*;
+    /// use resw::Writer;
+    ///
+    /// let mut writer = Writer::new(Vec::new());
+    /// writer.write_stmt(&Stmt::Debugger).unwrap();
+    /// assert_eq!(writer.into_string().unwrap(), "debugger;\n");
+    /// ```
+    pub fn into_string(self) -> Result<String, ::std::string::FromUtf8Error> {
+        String::from_utf8(self.out)
+    }
+}
+
+/// An `impl Write` that only keeps track of
+/// how many bytes have been written to it
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, IoError> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> Result<(), IoError> {
+        Ok(())
+    }
+}
+
+/// The quick and easy way to serialize a `Program` with
+/// the default settings, either through `to_string` or
+/// directly in a format string
+/// ```
+/// use resast::prelude::*;
+/// use resw::DisplayProgram;
+///
+/// let program = Program::Script(vec![ProgramPart::Stmt(Stmt::Debugger)]);
+/// assert_eq!(DisplayProgram(&program).to_string(), "debugger;\n\n");
+/// ```
+/// `String` and `Program` are both foreign types, so a
+/// `TryFrom<&Program> for String` impl isn't possible here
+pub struct DisplayProgram<'a>(pub &'a Program);
+
+impl<'a> ::std::fmt::Display for DisplayProgram<'a> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        let mut writer = Writer::new(Vec::new());
+        writer
+            .write_program(self.0)
+            .map_err(|_| ::std::fmt::Error)?;
+        let s = writer.into_string().map_err(|_| ::std::fmt::Error)?;
+        f.write_str(&s)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+
+    fn write_out<F>(f: F) -> String
+    where
+        F: FnOnce(&mut Writer<Vec<u8>>) -> Res,
+    {
+        write_out_with(Writer::new(Vec::new()), f)
+    }
+
+    fn write_out_with<F>(mut w: Writer<Vec<u8>>, f: F) -> String
+    where
+        F: FnOnce(&mut Writer<Vec<u8>>) -> Res,
+    {
+        f(&mut w).unwrap();
+        w.into_string().unwrap()
+    }
+
+    fn round_trip(js: &str) -> String {
+        let mut w = Writer::new(Vec::new());
+        for part in ressa::Parser::new(js).unwrap() {
+            w.write_part(&part.unwrap()).unwrap();
+        }
+        w.into_string().unwrap()
+    }
+
     #[test]
     fn write_empty_expression() {
         let mut f = write_str::WriteString::new();
         let mut w = Writer::new(f.generate_child());
         w.write_empty_stmt().unwrap();
-        let out = f.get_string_lossy();
+        let out = f.get_string().unwrap();
         assert_eq!(out, ";".to_string());
     }
 
@@ -1937,7 +3584,7 @@ mod test {
         let mut f = write_str::WriteString::new();
         let mut w = Writer::new(f.generate_child());
         w.write_debugger_stmt().unwrap();
-        let s = f.get_string_lossy();
+        let s = f.get_string().unwrap();
         assert_eq!(s, "debugger");
     }
 
@@ -1950,7 +3597,7 @@ mod test {
             &[VariableDecl::with_value("thing", Expr::boolean(false))],
         )
         .unwrap();
-        let s = f.get_string_lossy();
+        let s = f.get_string().unwrap();
         assert_eq!(s, "var thing = false;\n".to_string());
         let mut f = write_str::WriteString::new();
         let mut w = Writer::new(f.generate_child());
@@ -1963,7 +3610,1976 @@ mod test {
             ],
         )
         .unwrap();
-        let s = f.get_string_lossy();
+        let s = f.get_string().unwrap();
         assert_eq!(s, "let stuff, places, thing = false;\n");
     }
+    #[test]
+    fn write_computed_member_sequence() {
+        let member = MemberExpr::new(
+            Expr::ident("obj"),
+            Expr::Sequence(vec![Expr::ident("a"), Expr::ident("b")]),
+            true,
+        );
+        let s = write_out(|w| w.write_member_expr(&member));
+        assert_eq!(s, "obj[(a, b)]");
+    }
+
+    #[test]
+    fn write_exported_decl_new_lines() {
+        let func = Function::new(Some("Foo".to_string()), vec![], vec![], false, false);
+        let decl = Decl::Export(Box::new(ModExport::Default(DefaultExportDecl::Decl(
+            Decl::Function(func),
+        ))));
+        let s = write_out(|w| w.write_decl(&decl));
+        assert_eq!(s, "export default function Foo() { }\n");
+        let class = Class::new(Some("Bar".to_string()), None, vec![]);
+        let decl = Decl::Export(Box::new(ModExport::Named(NamedExportDecl::Decl(
+            Decl::Class(class),
+        ))));
+        let s = write_out(|w| w.write_decl(&decl));
+        assert_eq!(s, "export class Bar {\n}\n");
+    }
+
+    #[test]
+    fn write_default_export() {
+        let exp = DefaultExportDecl::Expr(Expr::number("42"));
+        let s = write_out(|w| w.write_default_export(&exp));
+        assert_eq!(s, "default 42;");
+        let func = Function::new(None, vec![], vec![], false, false);
+        let exp = DefaultExportDecl::Decl(Decl::Function(func));
+        let s = write_out(|w| w.write_default_export(&exp));
+        assert_eq!(s, "default function() { }\n");
+        let class = Class::new(None, None, vec![]);
+        let exp = DefaultExportDecl::Decl(Decl::Class(class));
+        let s = write_out(|w| w.write_default_export(&exp));
+        assert_eq!(s, "default class {\n}\n");
+    }
+
+    #[test]
+    fn write_part_trailing_new_line() {
+        let part = ProgramPart::Stmt(Stmt::Debugger);
+        let s = write_out(|w| w.write_part(&part));
+        assert_eq!(s, "debugger;\n\n");
+        let w = Writer::builder()
+            .trailing_newline_per_part(false)
+            .build(Vec::new());
+        let s = write_out_with(w, |w| w.write_part(&part));
+        assert_eq!(s, "debugger;\n");
+    }
+    #[test]
+    fn write_class_expr_new_line() {
+        let decls = vec![VariableDecl::with_value(
+            "x",
+            Expr::Class(Class::new(None, None, vec![])),
+        )];
+        let s = write_out(|w| w.write_variable_decls(&VariableKind::Let, &decls));
+        assert_eq!(s, "let x = class {\n};\n");
+    }
+    #[test]
+    fn write_function_semicolons() {
+        let func = Function::new(Some("f".to_string()), vec![], vec![], false, false);
+        let s = write_out(|w| w.write_decl(&Decl::Function(func.clone())));
+        assert_eq!(s, "function f() { }\n");
+        let s = write_out(|w| w.write_function(&func));
+        assert_eq!(s, "function f() { }");
+        let expr = Expr::Function(Function::new(None, vec![], vec![], false, false));
+        let decls = vec![VariableDecl::with_value("f", expr)];
+        let s = write_out(|w| w.write_variable_decls(&VariableKind::Let, &decls));
+        assert_eq!(s, "let f = function() { };\n");
+    }
+    #[test]
+    fn write_empty_loop_bodies() {
+        let stmt = Stmt::For(ForStmt {
+            init: None,
+            test: None,
+            update: None,
+            body: Box::new(Stmt::Empty),
+        });
+        let s = write_out(|w| w.write_stmt(&stmt));
+        assert_eq!(s, "for (;;);\n");
+        let stmt = Stmt::ForIn(ForInStmt {
+            left: LoopLeft::Variable(VariableKind::Var, VariableDecl::uninitialized("x")),
+            right: Expr::ident("obj"),
+            body: Box::new(Stmt::Empty),
+        });
+        let s = write_out(|w| w.write_stmt(&stmt));
+        assert_eq!(s, "for (var x in obj);\n");
+        let stmt = Stmt::ForOf(ForOfStmt {
+            left: LoopLeft::Pat(Pat::Identifier("x".to_string())),
+            right: Expr::ident("arr"),
+            body: Box::new(Stmt::Empty),
+            is_await: false,
+        });
+        let s = write_out(|w| w.write_stmt(&stmt));
+        assert_eq!(s, "for (x of arr);\n");
+        let stmt = Stmt::While(WhileStmt::new(Expr::ident("x"), Stmt::Empty));
+        let s = write_out(|w| w.write_stmt(&stmt));
+        assert_eq!(s, "while (x);\n");
+    }
+    #[test]
+    fn write_call_spread_args() {
+        let call = CallExpr::new(
+            Expr::ident("fn"),
+            vec![Expr::Spread(Box::new(Expr::ident("arr")))],
+        );
+        let s = write_out(|w| w.write_call_expr(&call));
+        assert_eq!(s, "fn(...arr)");
+        let call = CallExpr::new(
+            Expr::ident("fn"),
+            vec![
+                Expr::ident("a"),
+                Expr::Spread(Box::new(Expr::ident("b"))),
+                Expr::ident("c"),
+            ],
+        );
+        let s = write_out(|w| w.write_call_expr(&call));
+        assert_eq!(s, "fn(a, ...b, c)");
+    }
+    #[test]
+    fn write_optional_chain_member() {
+        let s = write_out(|w| {
+            w.write_optional_chain_member(&Expr::ident("a"), &Expr::ident("b"), false)
+        });
+        assert_eq!(s, "a?.b");
+        let s = write_out(|w| {
+            w.write_optional_chain_member(&Expr::ident("a"), &Expr::ident("key"), true)
+        });
+        assert_eq!(s, "a?.[key]");
+        let obj = Expr::member(Expr::ident("a"), Expr::ident("b"), false);
+        let s = write_out(|w| w.write_optional_chain_member(&obj, &Expr::ident("c"), false));
+        assert_eq!(s, "a.b?.c");
+        let obj = Expr::logical(Expr::ident("a"), LogicalOperator::Or, Expr::ident("b"));
+        let s = write_out(|w| w.write_optional_chain_member(&obj, &Expr::ident("c"), false));
+        assert_eq!(s, "(a || b)?.c");
+        let s = write_out(|w| {
+            w.write_optional_chain_member(&Expr::ident("a"), &Expr::ident("b"), false)?;
+            w.write_optional_chain_link(&Expr::ident("c"), false)
+        });
+        assert_eq!(s, "a?.b?.c");
+        let s = write_out(|w| {
+            w.write_optional_chain_member(&Expr::ident("a"), &Expr::ident("b"), false)?;
+            w.write_paren_list(&[])
+        });
+        assert_eq!(s, "a?.b()");
+        let s = write_out(|w| w.write_optional_chain_call(&Expr::ident("a"), &[]));
+        assert_eq!(s, "a?.()");
+    }
+    #[test]
+    fn write_import_expr() {
+        let s = write_out(|w| w.write_import_expr(&Expr::Literal(Literal::string("'./mod.js'"))));
+        assert_eq!(s, "import('./mod.js')");
+        let path = Expr::binary(
+            Expr::ident("dir"),
+            BinaryOperator::Plus,
+            Expr::ident("file"),
+        );
+        let s = write_out(|w| w.write_import_expr(&path));
+        assert_eq!(s, "import(dir + file)");
+        let seq = Expr::Sequence(vec![Expr::ident("a"), Expr::ident("b")]);
+        let s = write_out(|w| w.write_import_expr(&seq));
+        assert_eq!(s, "import((a, b))");
+    }
+    #[test]
+    fn write_optional_chain_call() {
+        let args = vec![Expr::ident("a"), Expr::ident("b")];
+        let s = write_out(|w| w.write_optional_chain_call(&Expr::ident("cb"), &args));
+        assert_eq!(s, "cb?.(a, b)");
+        let callee = Expr::member(Expr::ident("obj"), Expr::ident("method"), false);
+        let s = write_out(|w| w.write_optional_chain_call(&callee, &[]));
+        assert_eq!(s, "obj.method?.()");
+        let callee = Expr::logical(Expr::ident("a"), LogicalOperator::Or, Expr::ident("b"));
+        let s = write_out(|w| w.write_optional_chain_call(&callee, &[]));
+        assert_eq!(s, "(a || b)?.()");
+    }
+    #[test]
+    fn write_object_pattern_rest() {
+        let short_hand = |name: &str| {
+            ObjectPatPart::Assignment(Property::new(
+                PropertyKey::Expr(Expr::ident(name)),
+                PropertyValue::None,
+                PropertyKind::Init,
+                false,
+                false,
+                true,
+            ))
+        };
+        let rest = Pat::RestElement(Box::new(Pat::Identifier("rest".to_string())));
+        let decls = vec![VariableDecl::new(
+            Pat::Object(vec![short_hand("a"), ObjectPatPart::Rest(Box::new(rest))]),
+            Some(Expr::ident("obj")),
+        )];
+        let s = write_out(|w| w.write_variable_decls(&VariableKind::Const, &decls));
+        assert_eq!(s, "const {a, ...rest} = obj;\n");
+        let others = Pat::Identifier("others".to_string());
+        let decls = vec![VariableDecl::new(
+            Pat::Object(vec![
+                short_hand("a"),
+                short_hand("b"),
+                ObjectPatPart::Rest(Box::new(others)),
+            ]),
+            Some(Expr::ident("obj")),
+        )];
+        let s = write_out(|w| w.write_variable_decls(&VariableKind::Const, &decls));
+        assert_eq!(s, "const {a, b, ...others} = obj;\n");
+    }
+    #[test]
+    fn write_var_stmt() {
+        let stmt = Stmt::Var(vec![
+            VariableDecl::with_value("x", Expr::number("1")),
+            VariableDecl::with_value("y", Expr::number("2")),
+        ]);
+        let s = write_out(|w| w.write_stmt(&stmt));
+        assert_eq!(s, "var x = 1, y = 2;\n");
+        let a_in_b = Expr::binary(Expr::ident("a"), BinaryOperator::In, Expr::ident("b"));
+        let stmt = Stmt::Var(vec![VariableDecl::with_value("x", a_in_b.clone())]);
+        let s = write_out(|w| w.write_stmt(&stmt));
+        assert_eq!(s, "var x = a in b;\n");
+        let stmt = Stmt::For(ForStmt {
+            init: Some(LoopInit::Variable(
+                VariableKind::Var,
+                vec![VariableDecl::with_value("x", a_in_b)],
+            )),
+            test: None,
+            update: None,
+            body: Box::new(Stmt::Empty),
+        });
+        let s = write_out(|w| w.write_stmt(&stmt));
+        assert_eq!(s, "for (var x = (a in b);;);\n");
+    }
+    #[test]
+    fn builder_chaining() {
+        let mut b = Builder::new();
+        b.set_indent("  ")
+            .set_new_line("\r\n")
+            .set_quote('"')
+            .set_trailing_newline_per_part(false);
+        assert_eq!(b.indent, "  ");
+        assert_eq!(b.new_line, "\r\n");
+        assert_eq!(b.quote, Some('"'));
+        assert!(!b.trailing_newline_per_part);
+        b.reset_to_defaults();
+        assert_eq!(b.indent, "    ");
+        assert_eq!(b.new_line, "\n");
+        assert_eq!(b.quote, None);
+        assert!(b.trailing_newline_per_part);
+        let w: Writer<Vec<u8>> = b.indent("\t").new_line("\r\n").build(Vec::new());
+        assert_eq!(w.indent, "\t");
+        assert_eq!(w.new_line, "\r\n");
+    }
+    #[test]
+    fn write_template_expr() {
+        let template = TemplateLiteral::new(
+            vec![
+                TemplateElement::new(false, "things ".to_string(), "`things ${".to_string()),
+                TemplateElement::new(false, " and ".to_string(), "} and ${".to_string()),
+                TemplateElement::new(true, " stuff".to_string(), "} stuff`".to_string()),
+            ],
+            vec![Expr::ident("x"), Expr::number("0")],
+        );
+        let expr = Expr::Literal(Literal::Template(template));
+        let s = write_out(|w| w.write_expr(&expr));
+        assert_eq!(s, "`things ${x} and ${0} stuff`");
+        let template = TemplateLiteral::new(
+            vec![TemplateElement::new(
+                true,
+                "places".to_string(),
+                "`places`".to_string(),
+            )],
+            vec![],
+        );
+        let expr = Expr::Literal(Literal::Template(template));
+        let s = write_out(|w| w.write_expr(&expr));
+        assert_eq!(s, "`places`");
+    }
+    #[test]
+    fn write_regex_flags() {
+        for (pattern, flags, expected) in &[
+            ("pattern", "d", "/pattern/d"),
+            ("pattern", "gd", "/pattern/gd"),
+            ("pattern", "v", "/pattern/v"),
+            (r"[\p{Letter}]", "v", r"/[\p{Letter}]/v"),
+            ("pattern", "gzq", "/pattern/gzq"),
+        ] {
+            let regex = RegEx::new(pattern, flags);
+            let s = write_out(|w| w.write_regex(&regex));
+            assert_eq!(s, *expected);
+        }
+        let js = "let r = /pattern/gd;\n\n";
+        assert_eq!(round_trip(js), js);
+    }
+    #[test]
+    fn write_for_init_in() {
+        let cond = Expr::Conditional(ConditionalExpr {
+            test: Box::new(Expr::binary(
+                Expr::ident("x"),
+                BinaryOperator::In,
+                Expr::ident("y"),
+            )),
+            consequent: Box::new(Expr::ident("a")),
+            alternate: Box::new(Expr::ident("b")),
+        });
+        let stmt = Stmt::For(ForStmt {
+            init: Some(LoopInit::Expr(cond)),
+            test: None,
+            update: None,
+            body: Box::new(Stmt::Empty),
+        });
+        let s = write_out(|w| w.write_stmt(&stmt));
+        assert_eq!(s, "for ((x in y) ? a : b;;);\n");
+        let stmt = Stmt::For(ForStmt {
+            init: None,
+            test: Some(Expr::binary(
+                Expr::ident("a"),
+                BinaryOperator::In,
+                Expr::ident("b"),
+            )),
+            update: None,
+            body: Box::new(Stmt::Empty),
+        });
+        let s = write_out(|w| w.write_stmt(&stmt));
+        assert_eq!(s, "for (;a in b;);\n");
+    }
+    #[test]
+    fn write_labeled_empty_stmt() {
+        let stmt = Stmt::Labeled(LabeledStmt {
+            label: "loop".to_string(),
+            body: Box::new(Stmt::Empty),
+        });
+        let s = write_out(|w| w.write_stmt(&stmt));
+        assert_eq!(s, "loop: ;\n");
+        let stmt = Stmt::Labeled(LabeledStmt {
+            label: "outer".to_string(),
+            body: Box::new(Stmt::Labeled(LabeledStmt {
+                label: "inner".to_string(),
+                body: Box::new(Stmt::Empty),
+            })),
+        });
+        let s = write_out(|w| w.write_stmt(&stmt));
+        assert_eq!(s, "outer: inner: ;\n");
+        let stmt = Stmt::Labeled(LabeledStmt {
+            label: "label".to_string(),
+            body: Box::new(Stmt::Block(vec![])),
+        });
+        let s = write_out(|w| w.write_stmt(&stmt));
+        assert_eq!(s, "label: {\n    \n}\n");
+    }
+    #[test]
+    fn write_decorated_class() {
+        let method = Property::new(
+            PropertyKey::Expr(Expr::ident("bar")),
+            PropertyValue::Expr(Expr::Function(Function::new(
+                None,
+                vec![],
+                vec![],
+                false,
+                false,
+            ))),
+            PropertyKind::Method,
+            true,
+            false,
+            false,
+        );
+        let field = Property::new(
+            PropertyKey::Expr(Expr::ident("x")),
+            PropertyValue::None,
+            PropertyKind::Init,
+            false,
+            false,
+            true,
+        );
+        let class = Class::new(Some("Foo".to_string()), None, vec![method, field]);
+        let decorators = ClassDecorators {
+            class: vec![Expr::ident("sealed")],
+            members: vec![
+                vec![Expr::ident("log")],
+                vec![Expr::call(Expr::ident("observable"), vec![])],
+            ],
+        };
+        let w = Writer::builder().decorators(true).build(Vec::new());
+        let s = write_out_with(w, |w| w.write_decorated_class(&class, &decorators));
+        assert_eq!(
+            s,
+            "@sealed\nclass Foo {\n    @log\n    bar(){ }\n\n    @observable()\n    x\n}"
+        );
+        let s = write_out(|w| w.write_decorated_class(&class, &decorators));
+        assert_eq!(s, "class Foo {\n    bar(){ }\n\n    x\n}");
+        let s = write_out(|w| {
+            w.write_decorator(&Expr::member(Expr::ident("a"), Expr::ident("b"), false))?;
+            w.write_decorator(&Expr::Array(vec![]))
+        });
+        assert_eq!(s, "@a.b@([])");
+        let s = write_out(|w| {
+            w.write_decorator(&Expr::member(Expr::ident("a"), Expr::number("0"), true))
+        });
+        assert_eq!(s, "@(a[0])");
+        let s = write_out(|w| {
+            let chain = Expr::member(Expr::ident("a"), Expr::ident("b"), false);
+            w.write_decorator(&Expr::call(chain, vec![]))
+        });
+        assert_eq!(s, "@a.b()");
+        let s = write_out(|w| {
+            let callee = Expr::call(Expr::ident("a"), vec![]);
+            w.write_decorator(&Expr::call(callee, vec![]))
+        });
+        assert_eq!(s, "@(a()())");
+        let s = write_out(|w| {
+            let obj = Expr::call(Expr::ident("a"), vec![]);
+            w.write_decorator(&Expr::member(obj, Expr::ident("b"), false))
+        });
+        assert_eq!(s, "@(a().b)");
+    }
+    #[test]
+    fn write_new_target() {
+        let s = write_out(|w| {
+            w.write_meta_property(&MetaProperty::new("new".to_string(), "target".to_string()))
+        });
+        assert_eq!(s, "new.target");
+        let s = write_out(|w| {
+            w.write_new_expr(&NewExpr {
+                callee: Box::new(Expr::ident("target")),
+                arguments: vec![],
+            })
+        });
+        assert_eq!(s, "new target()");
+    }
+    #[test]
+    fn write_stmt_variants() {
+        let empty_block = "{\n    \n}";
+        let cases = vec![
+            (Stmt::Empty, ";".to_string()),
+            (Stmt::Debugger, "debugger;\n".to_string()),
+            (Stmt::Expr(Expr::ident("a")), "a;\n".to_string()),
+            (Stmt::Block(vec![]), empty_block.to_string()),
+            (
+                Stmt::With(WithStmt {
+                    object: Expr::ident("a"),
+                    body: Box::new(Stmt::Empty),
+                }),
+                "with (a) ;\n".to_string(),
+            ),
+            (Stmt::Return(None), "return;\n".to_string()),
+            (
+                Stmt::Return(Some(Expr::ident("a"))),
+                "return a;\n".to_string(),
+            ),
+            (
+                Stmt::Labeled(LabeledStmt {
+                    label: "a".to_string(),
+                    body: Box::new(Stmt::Empty),
+                }),
+                "a: ;\n".to_string(),
+            ),
+            (Stmt::Break(Some("a".to_string())), "break a;\n".to_string()),
+            (Stmt::Continue(None), "continue;\n".to_string()),
+            (
+                Stmt::If(IfStmt {
+                    test: Expr::ident("a"),
+                    consequent: Box::new(Stmt::Empty),
+                    alternate: None,
+                }),
+                format!("if (a) {}\n", empty_block),
+            ),
+            (
+                Stmt::Switch(SwitchStmt {
+                    discriminant: Expr::ident("a"),
+                    cases: vec![],
+                }),
+                "switch (a) { }\n".to_string(),
+            ),
+            (Stmt::Throw(Expr::ident("e")), "throw e;\n".to_string()),
+            (
+                Stmt::Try(TryStmt {
+                    block: vec![],
+                    handler: None,
+                    finalizer: Some(vec![]),
+                }),
+                format!("try {} finally {}\n", empty_block, empty_block),
+            ),
+            (
+                Stmt::DoWhile(DoWhileStmt {
+                    test: Expr::ident("a"),
+                    body: Box::new(Stmt::Empty),
+                }),
+                "do; while (a);\n".to_string(),
+            ),
+            (
+                Stmt::While(WhileStmt::new(Expr::ident("a"), Stmt::Empty)),
+                "while (a);\n".to_string(),
+            ),
+            (
+                Stmt::For(ForStmt {
+                    init: None,
+                    test: Some(Expr::ident("a")),
+                    update: None,
+                    body: Box::new(Stmt::Block(vec![])),
+                }),
+                format!("for (;a;) {}\n", empty_block),
+            ),
+            (
+                Stmt::ForIn(ForInStmt {
+                    left: LoopLeft::Pat(Pat::Identifier("a".to_string())),
+                    right: Expr::ident("b"),
+                    body: Box::new(Stmt::Empty),
+                }),
+                "for (a in b);\n".to_string(),
+            ),
+            (
+                Stmt::ForOf(ForOfStmt {
+                    left: LoopLeft::Pat(Pat::Identifier("a".to_string())),
+                    right: Expr::ident("b"),
+                    body: Box::new(Stmt::Empty),
+                    is_await: false,
+                }),
+                "for (a of b);\n".to_string(),
+            ),
+            (
+                Stmt::Var(vec![VariableDecl::uninitialized("a")]),
+                "var a;\n".to_string(),
+            ),
+        ];
+        for (stmt, expected) in cases {
+            let s = write_out(|w| w.write_stmt(&stmt));
+            assert_eq!(s, expected);
+        }
+    }
+    #[test]
+    fn write_for_in_of_bodies() {
+        let for_in = |body: Stmt| {
+            Stmt::ForIn(ForInStmt {
+                left: LoopLeft::Variable(VariableKind::Var, VariableDecl::uninitialized("x")),
+                right: Expr::ident("obj"),
+                body: Box::new(body),
+            })
+        };
+        let for_of = |body: Stmt| {
+            Stmt::ForOf(ForOfStmt {
+                left: LoopLeft::Variable(VariableKind::Let, VariableDecl::uninitialized("x")),
+                right: Expr::ident("arr"),
+                body: Box::new(body),
+                is_await: false,
+            })
+        };
+        let s = write_out(|w| w.write_stmt(&for_in(Stmt::Block(vec![]))));
+        assert_eq!(s, "for (var x in obj) {\n    \n}\n");
+        let s = write_out(|w| w.write_stmt(&for_in(Stmt::Expr(Expr::ident("x")))));
+        assert_eq!(s, "for (var x in obj) x;\n");
+        let s = write_out(|w| w.write_stmt(&for_of(Stmt::Block(vec![]))));
+        assert_eq!(s, "for (let x of arr) {\n    \n}\n");
+        let s = write_out(|w| w.write_stmt(&for_of(Stmt::Expr(Expr::ident("x")))));
+        assert_eq!(s, "for (let x of arr) x;\n");
+    }
+    #[test]
+    fn write_with_stmt_bodies() {
+        let with = |body: Stmt| {
+            Stmt::With(WithStmt {
+                object: Expr::ident("obj"),
+                body: Box::new(body),
+            })
+        };
+        let s = write_out(|w| w.write_stmt(&with(Stmt::Expr(Expr::ident("x")))));
+        assert_eq!(s, "with (obj) x;\n");
+        let s = write_out(|w| w.write_stmt(&with(Stmt::Block(vec![]))));
+        assert_eq!(s, "with (obj) {\n    \n}\n");
+        let if_stmt = Stmt::If(IfStmt {
+            test: Expr::ident("x"),
+            consequent: Box::new(Stmt::Block(vec![])),
+            alternate: None,
+        });
+        let s = write_out(|w| w.write_stmt(&with(if_stmt)));
+        assert_eq!(s, "with (obj) if (x) {\n    \n}\n");
+    }
+    #[test]
+    fn program_part_count_and_size() {
+        let cases = vec![
+            ("", 0),
+            ("var x = 1;", 1),
+            ("'use strict';\nfunction f() {\n}\nf();", 3),
+        ];
+        for (js, count) in cases {
+            let parts: Vec<ProgramPart> = ressa::Parser::new(js)
+                .unwrap()
+                .map(|p| p.unwrap())
+                .collect();
+            let program = Program::Script(parts);
+            let w = Writer::new(Vec::new());
+            assert_eq!(w.write_program_part_count(&program), count);
+            let size = w.estimate_output_size(&program).unwrap();
+            let s = write_out_with(w, |w| w.write_program(&program));
+            assert_eq!(size, s.len());
+        }
+    }
+    #[test]
+    fn write_import_specifier() {
+        let s = write_out(|w| {
+            w.write_import_specifier(&ImportSpecifier::Normal(
+                "Thing".to_string(),
+                Some("Stuff".to_string()),
+            ))
+        });
+        assert_eq!(s, "Thing as Stuff");
+        let s = write_out(|w| {
+            w.write_import_specifier(&ImportSpecifier::Namespace("Moment".to_string()))
+        });
+        assert_eq!(s, "* as Moment");
+        let s =
+            write_out(|w| w.write_import_specifier(&ImportSpecifier::Default("Stuff".to_string())));
+        assert_eq!(s, "Stuff");
+    }
+    #[test]
+    fn write_import_decl_forms() {
+        let import = |specifiers: Vec<ImportSpecifier>| {
+            let imp = ModImport {
+                specifiers,
+                source: Literal::string("'m'"),
+            };
+            write_out(|w| w.write_import_decl(&imp))
+        };
+        let default = || ImportSpecifier::Default("d".to_string());
+        let namespace = || ImportSpecifier::Namespace("ns".to_string());
+        let normal = |name: &str, local: Option<&str>| {
+            ImportSpecifier::Normal(name.to_string(), local.map(String::from))
+        };
+        assert_eq!(import(vec![]), "import 'm';");
+        assert_eq!(import(vec![default()]), "import d from 'm';");
+        assert_eq!(import(vec![namespace()]), "import * as ns from 'm';");
+        assert_eq!(
+            import(vec![normal("a", None), normal("b", Some("c"))]),
+            "import { a, b as c } from 'm';"
+        );
+        assert_eq!(
+            import(vec![default(), normal("a", None)]),
+            "import d, { a } from 'm';"
+        );
+        assert_eq!(
+            import(vec![default(), normal("a", None), normal("b", None)]),
+            "import d, { a, b } from 'm';"
+        );
+        assert_eq!(
+            import(vec![default(), namespace()]),
+            "import d, * as ns from 'm';"
+        );
+    }
+    #[test]
+    fn write_export_specifiers() {
+        let specifiers = vec![
+            ExportSpecifier::new("a".to_string(), None),
+            ExportSpecifier::new("b".to_string(), Some("c".to_string())),
+        ];
+        let decl = Decl::Export(Box::new(ModExport::Named(NamedExportDecl::Specifier(
+            specifiers.clone(),
+            None,
+        ))));
+        let s = write_out(|w| w.write_decl(&decl));
+        assert_eq!(s, "export {a, b as c};");
+        let decl = Decl::Export(Box::new(ModExport::Named(NamedExportDecl::Specifier(
+            specifiers,
+            Some(Literal::string("'mod'")),
+        ))));
+        let s = write_out(|w| w.write_decl(&decl));
+        assert_eq!(s, "export {a, b as c} from 'mod';");
+    }
+    #[test]
+    fn write_empty_export_specifiers() {
+        let decl = Decl::Export(Box::new(ModExport::Named(NamedExportDecl::Specifier(
+            vec![],
+            None,
+        ))));
+        let s = write_out(|w| w.write_decl(&decl));
+        assert_eq!(s, "export {};");
+        let decl = Decl::Export(Box::new(ModExport::Named(NamedExportDecl::Specifier(
+            vec![],
+            Some(Literal::string("'mod'")),
+        ))));
+        let s = write_out(|w| w.write_decl(&decl));
+        assert_eq!(s, "export {} from 'mod';");
+    }
+    #[test]
+    fn write_duplicate_export_specifiers() {
+        let specifiers = vec![
+            ExportSpecifier::new("a".to_string(), None),
+            ExportSpecifier::new("a".to_string(), None),
+        ];
+        let decl = Decl::Export(Box::new(ModExport::Named(NamedExportDecl::Specifier(
+            specifiers, None,
+        ))));
+        let s = write_out(|w| w.write_decl(&decl));
+        assert_eq!(s, "export {a, a};");
+    }
+    #[test]
+    fn write_all_export() {
+        let decl = Decl::Export(Box::new(ModExport::All(Literal::string("'./utils.js'"))));
+        let s = write_out(|w| w.write_decl(&decl));
+        assert_eq!(s, "export * from './utils.js';");
+    }
+    #[test]
+    fn write_all_as_export() {
+        let s = write_out(|w| w.write_all_as_export("utils", &Literal::string("'./utils.js'")));
+        assert_eq!(s, "* as utils from './utils.js';");
+    }
+    #[test]
+    fn write_paren_default_export() {
+        let sum = Expr::binary(Expr::ident("a"), BinaryOperator::Plus, Expr::ident("b"));
+        let exp = DefaultExportDecl::Expr(sum);
+        let s = write_out(|w| w.write_default_export(&exp));
+        assert_eq!(s, "default a + b;");
+        let w = Writer::builder()
+            .paren_default_export(true)
+            .build(Vec::new());
+        let s = write_out_with(w, |w| w.write_default_export(&exp));
+        assert_eq!(s, "default (a + b);");
+        let func = Function::new(None, vec![], vec![], false, false);
+        let exp = DefaultExportDecl::Expr(Expr::Function(func.clone()));
+        let w = Writer::builder()
+            .paren_default_export(true)
+            .build(Vec::new());
+        let s = write_out_with(w, |w| w.write_default_export(&exp));
+        assert_eq!(s, "default function() { };");
+        let exp = DefaultExportDecl::Decl(Decl::Function(func));
+        let w = Writer::builder()
+            .paren_default_export(true)
+            .build(Vec::new());
+        let s = write_out_with(w, |w| w.write_default_export(&exp));
+        assert_eq!(s, "default function() { }\n");
+    }
+    #[test]
+    fn write_empty_class_body_compact() {
+        let class = Class::new(Some("Foo".to_string()), None, vec![]);
+        let s = write_out(|w| w.write_class(&class));
+        assert_eq!(s, "class Foo {\n}");
+        let w = Writer::builder()
+            .empty_class_body_compact(true)
+            .build(Vec::new());
+        let s = write_out_with(w, |w| w.write_decl(&Decl::Class(class)));
+        assert_eq!(s, "class Foo { }\n");
+    }
+    #[test]
+    fn write_nullish_coalescing() {
+        let a_or_b = Expr::logical(Expr::ident("a"), LogicalOperator::Or, Expr::ident("b"));
+        let a_and_b = Expr::logical(Expr::ident("a"), LogicalOperator::And, Expr::ident("b"));
+        let c = Expr::ident("c");
+        let s = write_out(|w| w.write_nullish_coalescing(&Expr::ident("a"), &c));
+        assert_eq!(s, "a ?? c");
+        let s = write_out(|w| w.write_nullish_coalescing(&a_or_b, &c));
+        assert_eq!(s, "(a || b) ?? c");
+        let s = write_out(|w| w.write_nullish_coalescing(&a_and_b, &c));
+        assert_eq!(s, "(a && b) ?? c");
+        let s = write_out(|w| w.write_nullish_coalescing(&c, &a_or_b));
+        assert_eq!(s, "c ?? (a || b)");
+        let s = write_out(|w| w.write_nullish_coalescing(&c, &a_and_b));
+        assert_eq!(s, "c ?? (a && b)");
+    }
+    #[test]
+    fn write_computed_accessors() {
+        let empty_func = |params: Vec<FunctionArg>| {
+            PropertyValue::Expr(Expr::Function(Function::new(
+                None,
+                params,
+                vec![],
+                false,
+                false,
+            )))
+        };
+        let getter = Property::new(
+            PropertyKey::Expr(Expr::member(
+                Expr::ident("Symbol"),
+                Expr::ident("toPrimitive"),
+                false,
+            )),
+            empty_func(vec![]),
+            PropertyKind::Get,
+            true,
+            true,
+            false,
+        );
+        let setter = Property::new(
+            PropertyKey::Literal(Literal::string("'value'")),
+            empty_func(vec![FunctionArg::Pat(Pat::Identifier("v".to_string()))]),
+            PropertyKind::Set,
+            true,
+            true,
+            false,
+        );
+        let class = Class::new(
+            Some("A".to_string()),
+            None,
+            vec![getter.clone(), setter.clone()],
+        );
+        let s = write_out(|w| w.write_class(&class));
+        assert_eq!(
+            s,
+            "class A {\n    get [Symbol.toPrimitive](){ }\n\n    set ['value'](v){ }\n}"
+        );
+        let obj = vec![
+            ObjectProperty::Property(getter),
+            ObjectProperty::Property(setter),
+        ];
+        let s = write_out(|w| w.write_object_expr(&obj));
+        assert_eq!(s, "{get [Symbol.toPrimitive](){ }, set ['value'](v){ }}");
+    }
+    #[test]
+    fn write_computed_pattern_key() {
+        let s = round_trip("const {[computed]: alias} = obj;");
+        assert_eq!(s, "const {[computed]: alias} = obj;\n\n");
+        let key = PropertyKey::Pat(Pat::Identifier("a".to_string()));
+        let s = write_out(|w| w.write_property_key(&key, false));
+        assert_eq!(s, "a");
+    }
+    #[test]
+    fn write_binary_expr_stmt() {
+        let stmt = Stmt::Expr(Expr::binary(
+            Expr::ident("a"),
+            BinaryOperator::Plus,
+            Expr::ident("b"),
+        ));
+        let s = write_out(|w| w.write_stmt(&stmt));
+        assert_eq!(s, "a + b;\n");
+        let obj = Expr::Object(vec![ObjectProperty::Property(Property::new(
+            PropertyKey::Expr(Expr::ident("a")),
+            PropertyValue::Expr(Expr::number("1")),
+            PropertyKind::Init,
+            false,
+            false,
+            false,
+        ))]);
+        let stmt = Stmt::Expr(Expr::binary(obj, BinaryOperator::Plus, Expr::ident("b")));
+        let s = write_out(|w| w.write_stmt(&stmt));
+        assert_eq!(s, "({a: 1} + b);\n");
+    }
+    #[test]
+    fn write_power_of_unary_operands() {
+        let neg = |op| Expr::Unary(UnaryExpr::new(op, true, Expr::number("1")));
+        let pow = |left, right| Expr::binary(left, BinaryOperator::PowerOf, right);
+        let s = write_out(|w| w.write_expr(&pow(neg(UnaryOperator::Minus), Expr::number("2"))));
+        assert_eq!(s, "(-1) ** 2");
+        let s = write_out(|w| w.write_expr(&pow(neg(UnaryOperator::Plus), Expr::number("2"))));
+        assert_eq!(s, "(+1) ** 2");
+        let neg_two = Expr::Unary(UnaryExpr::new(
+            UnaryOperator::Minus,
+            true,
+            Expr::number("2"),
+        ));
+        let s = write_out(|w| w.write_expr(&pow(Expr::number("1"), neg_two)));
+        assert_eq!(s, "1 ** -2");
+    }
+    #[test]
+    fn builder_code_style() {
+        let js = "if (a) { b(\"c\"); }";
+        let styled = |w: Writer<Vec<u8>>| {
+            write_out_with(w, |w| {
+                for part in ressa::Parser::new(js).unwrap() {
+                    w.write_part(&part.unwrap())?;
+                }
+                Ok(())
+            })
+        };
+        let cases = vec![
+            (CodeStyle::Default, "if (a) {\n    b(\"c\");\n}\n\n"),
+            (CodeStyle::Prettier, "if (a) {\n  b(\"c\");\n}\n\n"),
+            (CodeStyle::StandardJS, "if (a) {\n  b('c')\n}\n\n"),
+            (CodeStyle::Minified, "if (a) {b(\"c\");}"),
+            (CodeStyle::AirBnB, "if (a) {\n  b('c');\n}\n\n"),
+        ];
+        for (style, expected) in cases {
+            let w = Writer::builder().code_style(style).build(Vec::new());
+            assert_eq!(styled(w), expected);
+        }
+        let w = Writer::builder()
+            .code_style(CodeStyle::Prettier)
+            .indent("\t")
+            .build(Vec::new());
+        assert_eq!(styled(w), "if (a) {\n\tb(\"c\");\n}\n\n");
+        let call = Expr::call(Expr::ident("f"), vec![Expr::ident("a")]);
+        let multi_line = |style: CodeStyle| {
+            let w = Writer::builder()
+                .code_style(style)
+                .multi_line_list_threshold(0)
+                .build(Vec::new());
+            write_out_with(w, |w| w.write_expr(&call))
+        };
+        assert_eq!(multi_line(CodeStyle::Prettier), "f(\n  a\n)");
+        assert_eq!(multi_line(CodeStyle::AirBnB), "f(\n  a,\n)");
+        assert_eq!(multi_line(CodeStyle::StandardJS), "f(\n  a\n)");
+        let obj = Expr::Object(vec![ObjectProperty::number("a", "1")]);
+        let w = Writer::builder()
+            .code_style(CodeStyle::Prettier)
+            .multi_line_object_threshold(0)
+            .build(Vec::new());
+        let s = write_out_with(w, |w| w.write_expr(&obj));
+        assert_eq!(s, "{\n  a: 1,\n}");
+    }
+    #[test]
+    fn write_arrow_expression_body() {
+        let arrow = |ret: Expr| ArrowFunctionExpr {
+            id: None,
+            params: vec![FunctionArg::Pat(Pat::Identifier("x".to_string()))],
+            body: ArrowFunctionBody::FunctionBody(vec![ProgramPart::Stmt(Stmt::Return(Some(ret)))]),
+            expression: false,
+            generator: false,
+            is_async: false,
+        };
+        let x_plus_one = Expr::binary(Expr::ident("x"), BinaryOperator::Plus, Expr::number("1"));
+        let func = arrow(x_plus_one);
+        let s = write_out(|w| w.write_arrow_function_expr(&func));
+        assert_eq!(s, "x => {\n    return x + 1;\n}");
+        let w = Writer::builder()
+            .arrow_expression_body(true)
+            .build(Vec::new());
+        let s = write_out_with(w, |w| w.write_arrow_function_expr(&func));
+        assert_eq!(s, "x => x + 1");
+        let obj = Expr::Object(vec![ObjectProperty::Property(Property::new(
+            PropertyKey::Expr(Expr::ident("a")),
+            PropertyValue::Expr(Expr::ident("x")),
+            PropertyKind::Init,
+            false,
+            false,
+            false,
+        ))]);
+        let w = Writer::builder()
+            .arrow_expression_body(true)
+            .build(Vec::new());
+        let s = write_out_with(w, |w| w.write_arrow_function_expr(&arrow(obj)));
+        assert_eq!(s, "x => ({a: x})");
+        let w = Writer::builder()
+            .arrow_expression_body(true)
+            .build(Vec::new());
+        let s = write_out_with(w, |w| {
+            w.write_arrow_function_expr(&arrow(Expr::ident("undefined")))
+        });
+        assert_eq!(s, "x => {\n    return undefined;\n}");
+    }
+    #[test]
+    fn writer_context_stack() {
+        let js = "function f() { for (var i = 0;;) { class A { m() { return 1; } } } }";
+        let mut w = Writer::new(Vec::new());
+        assert_eq!(w.context(), WriterContext::TopLevel);
+        for part in ressa::Parser::new(js).unwrap() {
+            w.write_part(&part.unwrap()).unwrap();
+        }
+        assert_eq!(w.context(), WriterContext::TopLevel);
+        assert_eq!(w.context.len(), 1);
+        assert!(!w.in_context(WriterContext::ForInit));
+    }
+    #[test]
+    fn write_destructuring_loop_left() {
+        let a_b = Pat::Array(vec![
+            Some(ArrayPatPart::Pat(Pat::Identifier("a".to_string()))),
+            Some(ArrayPatPart::Pat(Pat::Identifier("b".to_string()))),
+        ]);
+        let for_of = |left: LoopLeft| {
+            Stmt::ForOf(ForOfStmt {
+                left,
+                right: Expr::ident("pairs"),
+                body: Box::new(Stmt::Empty),
+                is_await: false,
+            })
+        };
+        let s = write_out(|w| w.write_stmt(&for_of(LoopLeft::Pat(a_b.clone()))));
+        assert_eq!(s, "for ([a, b] of pairs);\n");
+        let assign = Expr::Assignment(AssignmentExpr {
+            operator: AssignmentOperator::Equal,
+            left: AssignmentLeft::Pat(a_b),
+            right: Box::new(Expr::ident("x")),
+        });
+        let s = write_out(|w| w.write_stmt(&Stmt::Expr(assign)));
+        assert_eq!(s, "([a, b] = x);\n");
+        let s = round_trip("for ([a, b] of pairs);");
+        assert_eq!(s, "for ([a, b] of pairs);\n\n");
+    }
+    #[test]
+    fn write_arrow_params() {
+        let arrow = |params: Vec<FunctionArg>, is_async: bool| ArrowFunctionExpr {
+            id: None,
+            params,
+            body: ArrowFunctionBody::Expr(Box::new(Expr::ident("x"))),
+            expression: true,
+            generator: false,
+            is_async,
+        };
+        let id = || vec![FunctionArg::Pat(Pat::Identifier("x".to_string()))];
+        let destructure = || {
+            vec![FunctionArg::Pat(Pat::Object(vec![
+                ObjectPatPart::Assignment(Property::new(
+                    PropertyKey::Expr(Expr::ident("x")),
+                    PropertyValue::None,
+                    PropertyKind::Init,
+                    false,
+                    false,
+                    true,
+                )),
+            ]))]
+        };
+        let cases = vec![
+            (arrow(id(), false), "x => x"),
+            (arrow(id(), true), "async x => x"),
+            (arrow(destructure(), false), "({x}) => x"),
+            (arrow(destructure(), true), "async ({x}) => x"),
+            (arrow(vec![], false), "() => x"),
+            (arrow(vec![], true), "async () => x"),
+        ];
+        for (func, expected) in cases {
+            let s = write_out(|w| w.write_arrow_function_expr(&func));
+            assert_eq!(s, expected);
+        }
+    }
+    #[test]
+    fn write_short_hand_properties() {
+        let prop = |value: PropertyValue, short_hand: bool| {
+            Property::new(
+                PropertyKey::Expr(Expr::ident("x")),
+                value,
+                PropertyKind::Init,
+                false,
+                false,
+                short_hand,
+            )
+        };
+        let cases = vec![
+            (prop(PropertyValue::None, true), "x"),
+            (prop(PropertyValue::Expr(Expr::ident("x")), true), "x"),
+            (prop(PropertyValue::Expr(Expr::number("1")), true), "x = 1"),
+            (prop(PropertyValue::Expr(Expr::ident("y")), true), "x = y"),
+            (prop(PropertyValue::Expr(Expr::ident("y")), false), "x: y"),
+            (prop(PropertyValue::Expr(Expr::ident("x")), false), "x: x"),
+        ];
+        for (p, expected) in cases {
+            let s = write_out(|w| w.write_property(&p));
+            assert_eq!(s, expected);
+        }
+        let s = round_trip("({x, y = 1} = obj);");
+        assert_eq!(s, "({x, y = 1} = obj);\n\n");
+    }
+    #[test]
+    fn write_literal_member_objects() {
+        let to_string =
+            |obj: Expr| Expr::call(Expr::member(obj, Expr::ident("toString"), false), vec![]);
+        let s = write_out(|w| w.write_expr(&to_string(Expr::boolean(true))));
+        assert_eq!(s, "true.toString()");
+        let s = write_out(|w| w.write_expr(&to_string(Expr::boolean(false))));
+        assert_eq!(s, "false.toString()");
+        let s = write_out(|w| w.write_expr(&to_string(Expr::number("1"))));
+        assert_eq!(s, "(1).toString()");
+    }
+    #[test]
+    fn write_class_member_spacing() {
+        let method = |name: &str, kind: PropertyKind| {
+            Property::new(
+                PropertyKey::Expr(Expr::ident(name)),
+                PropertyValue::Expr(Expr::Function(Function::new(
+                    None,
+                    vec![],
+                    vec![],
+                    false,
+                    false,
+                ))),
+                kind,
+                true,
+                false,
+                false,
+            )
+        };
+        let class = Class::new(
+            Some("Foo".to_string()),
+            None,
+            vec![method("constructor", PropertyKind::Ctor)],
+        );
+        let s = write_out(|w| w.write_class(&class));
+        assert_eq!(s, "class Foo {\n    constructor(){ }\n}");
+        let class = Class::new(
+            Some("Foo".to_string()),
+            None,
+            vec![
+                method("a", PropertyKind::Method),
+                method("b", PropertyKind::Method),
+            ],
+        );
+        let s = write_out(|w| w.write_class(&class));
+        assert_eq!(s, "class Foo {\n    a(){ }\n\n    b(){ }\n}");
+    }
+    #[test]
+    fn write_while_bodies() {
+        let s = round_trip("while (true) x++;");
+        assert_eq!(s, "while (true) x++;\n\n");
+        let s = round_trip("while (true) {}");
+        assert_eq!(s, "while (true) {\n    \n}\n\n");
+        let s = round_trip("while (true) if (x) {}");
+        assert_eq!(s, "while (true) if (x) {\n    \n}\n\n");
+    }
+    #[test]
+    fn builder_clone() {
+        let builder = Writer::builder().indent("  ").decorators(true);
+        let copy = builder.clone().quote('"');
+        let first: Writer<Vec<u8>> = builder.build(Vec::new());
+        let second: Writer<Vec<u8>> = copy.build(Vec::new());
+        assert_eq!(first.indent, "  ");
+        assert_eq!(first.quote, None);
+        assert!(first.decorators);
+        assert_eq!(second.indent, "  ");
+        assert_eq!(second.quote, Some('"'));
+        assert!(second.decorators);
+    }
+    #[test]
+    fn write_xor_expr() {
+        let bin = |l: Expr, op: BinaryOperator, r: Expr| Expr::binary(l, op, r);
+        let a_xor_b = bin(Expr::ident("a"), BinaryOperator::XOr, Expr::ident("b"));
+        let s = write_out(|w| w.write_expr(&a_xor_b));
+        assert_eq!(s, "a ^ b");
+        let chained = bin(a_xor_b, BinaryOperator::XOr, Expr::ident("c"));
+        let s = write_out(|w| w.write_expr(&chained));
+        assert_eq!(s, "a ^ b ^ c");
+        let b_and_c = bin(Expr::ident("b"), BinaryOperator::And, Expr::ident("c"));
+        let mixed = bin(Expr::ident("a"), BinaryOperator::XOr, b_and_c);
+        let s = write_out(|w| w.write_expr(&mixed));
+        assert_eq!(s, "a ^ b & c");
+    }
+    #[test]
+    fn write_object_expr_stmts() {
+        let cases = vec![
+            "({});",
+            "({a: 1, b: 2});",
+            "({...spread});",
+            "({[computed]: val});",
+        ];
+        for js in cases {
+            let s = round_trip(js);
+            // without the parentheses this would be a block
+            assert!(s.starts_with("({"));
+            assert_eq!(s, format!("{}\n\n", js));
+        }
+    }
+    #[test]
+    fn write_new_function_expr() {
+        let func = Expr::Function(Function::new(None, vec![], vec![], false, false));
+        let new = NewExpr {
+            callee: Box::new(func.clone()),
+            arguments: vec![],
+        };
+        let s = write_out(|w| w.write_new_expr(&new));
+        assert_eq!(s, "new function() { }()");
+        let new = NewExpr {
+            callee: Box::new(Expr::call(func, vec![])),
+            arguments: vec![],
+        };
+        let s = write_out(|w| w.write_new_expr(&new));
+        assert_eq!(s, "new ((function() { })())()");
+        let s = round_trip("new function() {};");
+        assert_eq!(s, "new function() { }();\n\n");
+    }
+    #[test]
+    fn write_generator_star_spacing() {
+        let named = Function::new(Some("foo".to_string()), vec![], vec![], true, false);
+        let anon = Function::new(None, vec![], vec![], true, false);
+        let s = write_out(|w| w.write_function(&named));
+        assert_eq!(s, "function* foo() { }");
+        let s = write_out(|w| w.write_function(&anon));
+        assert_eq!(s, "function*() { }");
+        let w = Writer::builder()
+            .generator_star_spacing(GeneratorStarSpacing::BeforeName)
+            .build(Vec::new());
+        let s = write_out_with(w, |w| w.write_function(&named));
+        assert_eq!(s, "function *foo() { }");
+        let w = Writer::builder()
+            .generator_star_spacing(GeneratorStarSpacing::BeforeName)
+            .build(Vec::new());
+        let s = write_out_with(w, |w| w.write_function(&anon));
+        assert_eq!(s, "function*() { }");
+    }
+    #[test]
+    fn write_namespace_import_decl() {
+        let import = |specifiers: Vec<ImportSpecifier>| ModImport {
+            specifiers,
+            source: Literal::string("'mod'"),
+        };
+        let ns = || ImportSpecifier::Namespace("ns".to_string());
+        let s = write_out(|w| w.write_import_decl(&import(vec![ns()])));
+        assert_eq!(s, "import * as ns from 'mod';");
+        let with_default = import(vec![ImportSpecifier::Default("Foo".to_string()), ns()]);
+        let s = write_out(|w| w.write_import_decl(&with_default));
+        assert_eq!(s, "import Foo, * as ns from 'mod';");
+    }
+    #[test]
+    fn writer_with_options() {
+        let call = Expr::call(Expr::ident("f"), vec![Expr::string("'a'")]);
+        let block = vec![ProgramPart::Stmt(Stmt::Expr(call))];
+        let mut w = Writer::new(Vec::new());
+        let opts = WriterOptions {
+            indent: Some("\t".to_string()),
+            new_line: Some("\r\n".to_string()),
+            quote: Some('"'),
+        };
+        w.with_options(&opts, |w| w.write_block_stmt(&block))
+            .unwrap();
+        assert_eq!(w.indent, "    ");
+        assert_eq!(w.new_line, "\n");
+        assert_eq!(w.quote, None);
+        w.write_block_stmt(&block).unwrap();
+        let s = w.into_string().unwrap();
+        assert_eq!(s, "{\r\n\tf(\"a\");\r\n}{\n    f('a');\n}");
+    }
+    #[test]
+    fn write_nested_ternary() {
+        let ternary = |test: &str, consequent: &str, alternate: Expr| {
+            Expr::Conditional(ConditionalExpr {
+                test: Box::new(Expr::ident(test)),
+                consequent: Box::new(Expr::ident(consequent)),
+                alternate: Box::new(alternate),
+            })
+        };
+        let two = ternary("a", "b", ternary("c", "d", Expr::ident("e")));
+        let three = ternary(
+            "a",
+            "b",
+            ternary("c", "d", ternary("e", "f", Expr::ident("g"))),
+        );
+        let s = write_out(|w| w.write_expr(&two));
+        assert_eq!(s, "a ? b : c ? d : e");
+        let s = write_out(|w| w.write_expr(&three));
+        assert_eq!(s, "a ? b : c ? d : e ? f : g");
+        let explicit = || {
+            Writer::builder()
+                .explicit_nested_ternary(true)
+                .build(Vec::new())
+        };
+        let s = write_out_with(explicit(), |w| w.write_expr(&two));
+        assert_eq!(s, "a ? b : (c ? d : e)");
+        let s = write_out_with(explicit(), |w| w.write_expr(&three));
+        assert_eq!(s, "a ? b : (c ? d : (e ? f : g))");
+    }
+    #[test]
+    fn write_instanceof_expr() {
+        let cases = vec![
+            "a instanceof Foo;",
+            "x instanceof Uint8Array;",
+            "new Foo() instanceof Foo;",
+            "!(x instanceof Foo);",
+        ];
+        for js in cases {
+            let s = round_trip(js);
+            assert_eq!(s, format!("{}\n\n", js));
+        }
+    }
+    #[test]
+    fn write_regex_divisor() {
+        let re = Expr::Literal(Literal::RegEx(RegEx::new("re", "g")));
+        let div = Expr::binary(Expr::ident("a"), BinaryOperator::Over, re.clone());
+        let s = write_out(|w| w.write_expr(&div));
+        assert_eq!(s, "a / (/re/g)");
+        let times = Expr::binary(Expr::ident("a"), BinaryOperator::Times, re);
+        let s = write_out(|w| w.write_expr(&times));
+        assert_eq!(s, "a * /re/g");
+    }
+    #[test]
+    fn write_sequence_exprs() {
+        let seq = || Expr::Sequence(vec![Expr::ident("a"), Expr::ident("b")]);
+        let s = write_out(|w| w.write_stmt(&Stmt::Expr(seq())));
+        assert_eq!(s, "a, b;\n");
+        let s = write_out(|w| w.write_expr(&seq()));
+        assert_eq!(s, "a, b");
+        let call = Expr::call(Expr::ident("f"), vec![seq(), Expr::ident("c")]);
+        let s = write_out(|w| w.write_expr(&call));
+        assert_eq!(s, "f((a, b), c)");
+        let decls = vec![VariableDecl::with_value("x", seq())];
+        let s = write_out(|w| w.write_variable_decls(&VariableKind::Let, &decls));
+        assert_eq!(s, "let x = (a, b);\n");
+        let s = write_out(|w| w.write_expr(&Expr::Array(vec![Some(seq())])));
+        assert_eq!(s, "[(a, b)]");
+        let s = write_out(|w| w.write_paren_list(&[Expr::ident("a"), Expr::ident("b")]));
+        assert_eq!(s, "(a, b)");
+        let s = round_trip("x = (a, b), c;");
+        assert_eq!(s, "x = (a, b), c;\n\n");
+        let s = round_trip("({}, a);");
+        assert_eq!(s, "({}, a);\n\n");
+    }
+    #[test]
+    fn write_async_generator_methods() {
+        // RESSA can't parse `async *gen() {}` so these are built by hand
+        let method = Property::new(
+            PropertyKey::Expr(Expr::ident("gen")),
+            PropertyValue::Expr(Expr::Function(Function::new(
+                None,
+                vec![],
+                vec![],
+                true,
+                true,
+            ))),
+            PropertyKind::Method,
+            true,
+            false,
+            false,
+        );
+        let obj = Expr::Object(vec![ObjectProperty::Property(method.clone())]);
+        let s = write_out(|w| w.write_expr(&obj));
+        assert_eq!(s, "{async *gen(){ }}");
+        let class = Class::new(Some("Foo".to_string()), None, vec![method]);
+        let s = write_out(|w| w.write_class(&class));
+        assert_eq!(s, "class Foo {\n    async *gen(){ }\n}");
+    }
+    #[test]
+    fn write_string_expr_stmt() {
+        let body = vec![ProgramPart::Stmt(Stmt::Expr(Expr::string(
+            "'a string expression statement'",
+        )))];
+        let func = Function::new(Some("f".to_string()), vec![], body, false, false);
+        let s = write_out(|w| w.write_function(&func));
+        assert_eq!(
+            s,
+            "function f() {\n    ('a string expression statement');\n}"
+        );
+        let s = round_trip("function f() { 'use strict'; }");
+        assert_eq!(s, "function f() {\n    'use strict';\n}\n\n");
+    }
+    #[test]
+    fn write_tagged_template_tags() {
+        let quasi = || {
+            TemplateLiteral::new(
+                vec![TemplateElement::new(
+                    true,
+                    "t".to_string(),
+                    "`t`".to_string(),
+                )],
+                vec![],
+            )
+        };
+        let tagged = |tag: Expr| TaggedTemplateExpr {
+            tag: Box::new(tag),
+            quasi: quasi(),
+        };
+        let cases = vec![
+            (
+                Expr::member(Expr::ident("obj"), Expr::ident("method"), false),
+                "obj.method`t`",
+            ),
+            (Expr::call(Expr::ident("fn"), vec![]), "fn()`t`"),
+            (
+                Expr::Conditional(ConditionalExpr {
+                    test: Box::new(Expr::ident("cond")),
+                    consequent: Box::new(Expr::ident("fn1")),
+                    alternate: Box::new(Expr::ident("fn2")),
+                }),
+                "(cond ? fn1 : fn2)`t`",
+            ),
+            (
+                Expr::Assignment(AssignmentExpr {
+                    operator: AssignmentOperator::Equal,
+                    left: AssignmentLeft::Expr(Box::new(Expr::ident("a"))),
+                    right: Box::new(Expr::ident("fn")),
+                }),
+                "(a = fn)`t`",
+            ),
+            (
+                Expr::Sequence(vec![Expr::ident("a"), Expr::ident("fn")]),
+                "(a, fn)`t`",
+            ),
+            (
+                Expr::logical(Expr::ident("fn1"), LogicalOperator::Or, Expr::ident("fn2")),
+                "(fn1 || fn2)`t`",
+            ),
+        ];
+        for (tag, expected) in cases {
+            let s = write_out(|w| w.write_tagged_template(&tagged(tag)));
+            assert_eq!(s, expected);
+        }
+    }
+    #[test]
+    fn write_interface() {
+        let member = |name: &str, ty: &str| InterfaceMember {
+            name: name.to_string(),
+            type_annotation: ty.to_string(),
+        };
+        let iface = Interface {
+            name: "Foo".to_string(),
+            members: vec![member("bar()", "void"), member("baz", "string")],
+        };
+        let ts = || Writer::builder().typescript_mode(true).build(Vec::new());
+        let s = write_out_with(ts(), |w| w.write_interface(&iface));
+        assert_eq!(s, "interface Foo {\n    bar(): void;\n    baz: string;\n}");
+        let empty = Interface {
+            name: "Empty".to_string(),
+            members: vec![],
+        };
+        let s = write_out_with(ts(), |w| w.write_interface(&empty));
+        assert_eq!(s, "interface Empty {\n}");
+        let s = write_out(|w| w.write_interface(&iface));
+        assert_eq!(s, "");
+    }
+    #[test]
+    fn write_try_stmt_combinations() {
+        let try_stmt = |param: Option<Pat>, catch: bool, finally: bool| TryStmt {
+            block: vec![],
+            handler: if catch {
+                Some(CatchClause {
+                    param,
+                    body: vec![],
+                })
+            } else {
+                None
+            },
+            finalizer: if finally { Some(vec![]) } else { None },
+        };
+        let e = || Some(Pat::Identifier("e".to_string()));
+        let s = write_out(|w| w.write_try_stmt(&try_stmt(None, false, true)));
+        assert_eq!(s, "try {\n    \n} finally {\n    \n}");
+        let s = write_out(|w| w.write_try_stmt(&try_stmt(e(), true, false)));
+        assert_eq!(s, "try {\n    \n} catch (e) {\n    \n}");
+        let s = write_out(|w| w.write_try_stmt(&try_stmt(e(), true, true)));
+        assert_eq!(s, "try {\n    \n} catch (e) {\n    \n} finally {\n    \n}");
+        let s = write_out(|w| w.write_try_stmt(&try_stmt(None, true, false)));
+        assert_eq!(s, "try {\n    \n} catch {\n    \n}");
+    }
+    #[test]
+    fn optional_catch_binding_round_trip() {
+        let s = round_trip("try {} catch {}");
+        assert_eq!(s, "try {\n    \n} catch {\n    \n}\n\n");
+        assert!(!s.contains("catch("));
+        assert!(!s.contains("catch ()"));
+    }
+    #[test]
+    fn write_object_expr_multiline() {
+        let obj = Expr::Object(vec![
+            ObjectProperty::number("a", "1"),
+            ObjectProperty::Spread(Box::new(Expr::Spread(Box::new(Expr::ident("b"))))),
+            ObjectProperty::number("c", "2"),
+        ]);
+        let s = write_out(|w| w.write_expr(&obj));
+        assert_eq!(s, "{a: 1, ...b, c: 2}");
+        let w = Writer::builder()
+            .multi_line_object_threshold(2)
+            .build(Vec::new());
+        let s = write_out_with(w, |w| w.write_expr(&obj));
+        assert_eq!(s, "{a: 1, ...b, c: 2}");
+        let w = Writer::builder()
+            .multi_line_object_threshold(1)
+            .build(Vec::new());
+        let s = write_out_with(w, |w| w.write_expr(&obj));
+        assert_eq!(s, "{\n    a: 1,\n    ...b,\n    c: 2\n}");
+        let w = Writer::builder()
+            .multi_line_object_threshold(1)
+            .trailing_commas(TrailingCommaPolicy::Es5)
+            .build(Vec::new());
+        let s = write_out_with(w, |w| w.write_expr(&obj));
+        assert_eq!(s, "{\n    a: 1,\n    ...b,\n    c: 2,\n}");
+        let w = Writer::builder()
+            .trailing_commas(TrailingCommaPolicy::All)
+            .build(Vec::new());
+        let s = write_out_with(w, |w| w.write_expr(&obj));
+        assert_eq!(s, "{a: 1, ...b, c: 2}");
+    }
+    #[test]
+    fn trailing_commas_multi_line_lists() {
+        let writer = |policy: TrailingCommaPolicy| {
+            Writer::builder()
+                .multi_line_list_threshold(1)
+                .trailing_commas(policy)
+                .build(Vec::new())
+        };
+        let (never, es5, all) = (
+            TrailingCommaPolicy::Never,
+            TrailingCommaPolicy::Es5,
+            TrailingCommaPolicy::All,
+        );
+        let arr = Expr::Array(vec![Some(Expr::ident("a")), Some(Expr::ident("b"))]);
+        let s = write_out_with(writer(never), |w| w.write_expr(&arr));
+        assert_eq!(s, "[\n    a,\n    b\n]");
+        let s = write_out_with(writer(es5), |w| w.write_expr(&arr));
+        assert_eq!(s, "[\n    a,\n    b,\n]");
+        let holes = Expr::Array(vec![Some(Expr::ident("a")), None]);
+        let s = write_out_with(writer(never), |w| w.write_expr(&holes));
+        assert_eq!(s, "[\n    a,\n    ,\n]");
+        let short = Expr::Array(vec![Some(Expr::ident("a"))]);
+        let s = write_out_with(writer(es5), |w| w.write_expr(&short));
+        assert_eq!(s, "[a]");
+
+        let call = Expr::call(Expr::ident("f"), vec![Expr::ident("a"), Expr::ident("b")]);
+        let s = write_out_with(writer(es5), |w| w.write_expr(&call));
+        assert_eq!(s, "f(\n    a,\n    b\n)");
+        let s = write_out_with(writer(all), |w| w.write_expr(&call));
+        assert_eq!(s, "f(\n    a,\n    b,\n)");
+
+        let param = |name: &str| FunctionArg::Pat(Pat::Identifier(name.to_string()));
+        let params = vec![param("a"), param("b")];
+        let s = write_out_with(writer(es5), |w| w.write_function_args(&params));
+        assert_eq!(s, "(\n    a,\n    b\n)");
+        let s = write_out_with(writer(all), |w| w.write_function_args(&params));
+        assert_eq!(s, "(\n    a,\n    b,\n)");
+        let rest = vec![
+            param("a"),
+            FunctionArg::Pat(Pat::RestElement(Box::new(Pat::Identifier("b".to_string())))),
+        ];
+        let s = write_out_with(writer(all), |w| w.write_function_args(&rest));
+        assert_eq!(s, "(\n    a,\n    ...b\n)");
+
+        let specifiers = vec![
+            ExportSpecifier::new("a".to_string(), None),
+            ExportSpecifier::new("b".to_string(), None),
+        ];
+        let s = write_out_with(writer(never), |w| {
+            w.write_export_specifiers(&specifiers, &None)
+        });
+        assert_eq!(s, "{\n    a,\n    b\n};");
+        let s = write_out_with(writer(es5), |w| {
+            w.write_export_specifiers(&specifiers, &None)
+        });
+        assert_eq!(s, "{\n    a,\n    b,\n};");
+
+        let imp = ModImport {
+            specifiers: vec![
+                ImportSpecifier::Default("d".to_string()),
+                ImportSpecifier::Normal("a".to_string(), None),
+                ImportSpecifier::Normal("b".to_string(), None),
+            ],
+            source: Literal::string("'m'"),
+        };
+        let s = write_out_with(writer(never), |w| w.write_import_decl(&imp));
+        assert_eq!(s, "import d, {\n    a,\n    b\n} from 'm';");
+        let s = write_out_with(writer(es5), |w| w.write_import_decl(&imp));
+        assert_eq!(s, "import d, {\n    a,\n    b,\n} from 'm';");
+    }
+    #[test]
+    fn indent_accessors() {
+        let mut w = Writer::builder().indent("\t").build(Vec::new());
+        assert_eq!(w.indent_level(), 0);
+        assert_eq!(w.indent_string(), "\t");
+        assert_eq!(w.current_indent_str(), "");
+        w.write_open_brace().unwrap();
+        w.write_open_brace().unwrap();
+        assert_eq!(w.indent_level(), 2);
+        assert_eq!(w.current_indent_str(), "\t\t");
+        w.write_close_brace().unwrap();
+        w.write_close_brace().unwrap();
+        assert_eq!(w.indent_level(), 0);
+        let body = vec![ProgramPart::Stmt(Stmt::Return(None))];
+        w.write_function_body(&body).unwrap();
+        assert_eq!(w.indent_level(), 0);
+        w.set_indent_level(3);
+        assert_eq!(w.indent_level(), 3);
+        assert_eq!(w.current_indent_str(), "\t\t\t");
+    }
+    #[test]
+    fn binary_precedence_wrapping() {
+        assert_eq!(round_trip("a + b && c;"), "a + b && c;\n\n");
+        assert_eq!(round_trip("a && b + c;"), "a && b + c;\n\n");
+        assert_eq!(round_trip("a * b + c * d;"), "a * b + c * d;\n\n");
+        assert_eq!(round_trip("(a + b) * c;"), "(a + b) * c;\n\n");
+        assert_eq!(round_trip("a - (b - c);"), "a - (b - c);\n\n");
+        assert_eq!(round_trip("a - b - c;"), "a - b - c;\n\n");
+        assert_eq!(round_trip("(a && b) + c;"), "(a && b) + c;\n\n");
+        assert_eq!(round_trip("a + b + c;"), "a + b + c;\n\n");
+        assert_eq!(round_trip("a + b * c;"), "a + b * c;\n\n");
+        assert_eq!(round_trip("a * (b + c);"), "a * (b + c);\n\n");
+        assert_eq!(round_trip("a ** b ** c;"), "a ** b ** c;\n\n");
+        assert_eq!(round_trip("(a ** b) ** c;"), "(a ** b) ** c;\n\n");
+        assert_eq!(round_trip("a < b == c < d;"), "a < b == c < d;\n\n");
+    }
+    #[test]
+    fn write_member_of_new() {
+        assert_eq!(round_trip("new Foo().bar;"), "new Foo().bar;\n\n");
+        assert_eq!(round_trip("(new Foo).bar;"), "new Foo().bar;\n\n");
+        assert_eq!(round_trip("new Foo.Bar();"), "new Foo.Bar();\n\n");
+        let member = Expr::Member(MemberExpr {
+            object: Box::new(Expr::New(NewExpr {
+                callee: Box::new(Expr::ident("Foo")),
+                arguments: vec![],
+            })),
+            property: Box::new(Expr::ident("bar")),
+            computed: false,
+        });
+        let s = write_out(|w| w.write_expr(&member));
+        assert_eq!(s, "new Foo().bar");
+    }
+    #[test]
+    fn write_arrow_expr_body_parens() {
+        assert_eq!(round_trip("x => x + 1;"), "x => x + 1;\n\n");
+        assert_eq!(round_trip("x => x * 2 + 1;"), "x => x * 2 + 1;\n\n");
+        assert_eq!(
+            round_trip("x => x > 0 ? x : -x;"),
+            "x => x > 0 ? x : -x;\n\n"
+        );
+        assert_eq!(round_trip("x => ({} + x);"), "x => ({} + x);\n\n");
+    }
+    #[test]
+    fn write_block_stmt_then_stmt() {
+        assert_eq!(
+            round_trip("function f() { { a; } return; }"),
+            "function f() {\n    {\n        a;\n    }\n    return;\n}\n\n"
+        );
+    }
+    #[test]
+    fn write_import_part_single_semicolon() {
+        let part = ProgramPart::Decl(Decl::Import(Box::new(ModImport {
+            specifiers: vec![ImportSpecifier::Default("React".to_string())],
+            source: Literal::string("'react'"),
+        })));
+        let w = Writer::builder()
+            .trailing_newline_per_part(false)
+            .build(Vec::new());
+        let s = write_out_with(w, |w| w.write_part(&part));
+        assert_eq!(s, "import React from 'react';");
+    }
+    #[test]
+    fn write_class_extends() {
+        let class = |super_class: Expr| Class {
+            id: Some("Foo".to_string()),
+            super_class: Some(Box::new(super_class)),
+            body: vec![],
+        };
+        let s = write_out(|w| w.write_class(&class(Expr::Literal(Literal::Null))));
+        assert_eq!(s, "class Foo extends null {\n}");
+        let either = Expr::Logical(LogicalExpr::new(
+            LogicalOperator::Or,
+            Expr::ident("a"),
+            Expr::ident("b"),
+        ));
+        let s = write_out(|w| w.write_class(&class(either)));
+        assert_eq!(s, "class Foo extends (a || b) {\n}");
+        let member = Expr::Member(MemberExpr {
+            object: Box::new(Expr::ident("a")),
+            property: Box::new(Expr::ident("B")),
+            computed: false,
+        });
+        let s = write_out(|w| w.write_class(&class(member)));
+        assert_eq!(s, "class Foo extends a.B {\n}");
+    }
+    #[test]
+    fn display_program() {
+        let program = Program::Script(vec![
+            ProgramPart::Stmt(Stmt::Expr(Expr::ident("a"))),
+            ProgramPart::Stmt(Stmt::Debugger),
+        ]);
+        assert_eq!(
+            format!("{}", DisplayProgram(&program)),
+            "a;\n\ndebugger;\n\n"
+        );
+    }
+    #[test]
+    fn write_template_round_trip() {
+        let js = "x = `a${b}c${d + e}f`;\n\n";
+        let first = round_trip(js);
+        assert_eq!(first, js);
+        assert_eq!(round_trip(&first), first);
+        assert_eq!(round_trip("x = ``;"), "x = ``;\n\n");
+    }
+    #[test]
+    fn write_numeric_separators() {
+        for n in &["1_000_000", "1_000n", "0xFF_FF", "0.000_1"] {
+            let s = write_out(|w| w.write_literal(&Literal::number(n)));
+            assert_eq!(&s, n);
+            let w = Writer::builder().quote('"').build(Vec::new());
+            let s = write_out_with(w, |w| w.write_literal(&Literal::number(n)));
+            assert_eq!(&s, n);
+        }
+    }
+    #[test]
+    fn write_bigint_literal() {
+        let s = write_out(|w| w.write_literal(&Literal::number("42n")));
+        assert_eq!(s, "42n");
+        let member = Expr::member(Expr::number("42n"), Expr::ident("toString"), false);
+        let s = write_out(|w| w.write_expr(&Expr::call(member, vec![])));
+        assert_eq!(s, "(42n).toString()");
+    }
+    #[test]
+    fn write_private_method() {
+        let method = Property::new(
+            PropertyKey::Expr(Expr::ident("#foo")),
+            PropertyValue::Expr(Expr::Function(Function::new(
+                None,
+                vec![],
+                vec![],
+                false,
+                false,
+            ))),
+            PropertyKind::Method,
+            true,
+            false,
+            false,
+        );
+        let class = Class::new(Some("Foo".to_string()), None, vec![method]);
+        let s = write_out(|w| w.write_class(&class));
+        assert_eq!(s, "class Foo {\n    #foo(){ }\n}");
+    }
+    #[test]
+    fn minify() {
+        let minified = |js: &str| {
+            let w = Writer::builder().minify(true).build(Vec::new());
+            write_out_with(w, |w| {
+                for part in ressa::Parser::new(js).unwrap() {
+                    w.write_part(&part.unwrap())?;
+                }
+                Ok(())
+            })
+        };
+        assert_eq!(minified("a + b * c;"), "a+b*c;");
+        assert_eq!(minified("a - -b;"), "a- -b;");
+        assert_eq!(minified("a + ++b;"), "a+ ++b;");
+        assert_eq!(minified("a in b;"), "a in b;");
+        assert_eq!(minified("a < !--b;"), "a< ! --b;");
+        assert_eq!(minified("a << !b;"), "a<< !b;");
+        assert_eq!(minified("a-- > b;"), "a-- >b;");
+        assert_eq!(minified("a + b-- >> c;"), "a+b-- >>c;");
+        assert_eq!(minified("a && b || c;"), "a&&b||c;");
+        assert_eq!(minified("a += -b;"), "a+=-b;");
+        assert_eq!(minified("a ? b : c;"), "a?b:c;");
+        assert_eq!(minified("f = (x) => x * 2;"), "f=x=>x*2;");
+        assert_eq!(
+            minified("function f(x) {\n    return x;\n}\nf(1);"),
+            "function f(x) {return x;}f(1);"
+        );
+    }
+    #[test]
+    fn byte_offset() {
+        let mut w = Writer::new(Vec::new());
+        assert_eq!(w.byte_offset(), 0);
+        w.write_stmt(&Stmt::Debugger).unwrap();
+        assert_eq!(w.byte_offset(), "debugger;\n".len());
+        w.write_expr(&Expr::ident("abc")).unwrap();
+        assert_eq!(w.byte_offset(), w.into_inner().len());
+    }
+    #[test]
+    fn position() {
+        let mut w = Writer::new(Vec::new());
+        assert_eq!(w.position(), (0, 0));
+        w.write_expr(&Expr::ident("abc")).unwrap();
+        assert_eq!(w.position(), (0, 3));
+        w.write_block_stmt(&[ProgramPart::Stmt(Stmt::Debugger)])
+            .unwrap();
+        assert_eq!(w.position(), (2, 1));
+        w.write_new_line().unwrap();
+        assert_eq!(w.position(), (3, 0));
+        w.write_literal(&Literal::string("'é'")).unwrap();
+        assert_eq!(w.position(), (3, 3));
+        w.write_literal(&Literal::string("'😀'")).unwrap();
+        assert_eq!(w.position(), (3, 7));
+    }
+    #[test]
+    fn source_map() {
+        let mut w = Writer::new(Vec::new()).with_source_map("in.js");
+        w.add_source_mapping(0, 0);
+        w.write_stmt(&Stmt::Debugger).unwrap();
+        w.add_source_mapping(1, 2);
+        w.write_stmt(&Stmt::Expr(Expr::ident("a"))).unwrap();
+        let map = w.source_map().unwrap();
+        assert_eq!(map.mappings()[1].generated_line, 1);
+        assert_eq!(map.mappings()[1].generated_column, 0);
+        assert_eq!(
+            map.to_json(),
+            r#"{"version":3,"sources":["in.js"],"names":[],"mappings":"AAAA;AACE"}"#
+        );
+        let mut w = Writer::new(Vec::new());
+        w.add_source_mapping(0, 0);
+        assert!(w.source_map().is_none());
+    }
+    #[test]
+    fn semicolons_never() {
+        let asi = |js: &str| {
+            let w = Writer::builder()
+                .semicolons(SemicolonPolicy::Never)
+                .trailing_newline_per_part(false)
+                .build(Vec::new());
+            write_out_with(w, |w| {
+                for part in ressa::Parser::new(js).unwrap() {
+                    w.write_part(&part.unwrap())?;
+                }
+                Ok(())
+            })
+        };
+        assert_eq!(
+            asi("let a = 1; a++; debugger;"),
+            "let a = 1\na++\ndebugger\n"
+        );
+        assert_eq!(asi("for (;;);"), "for (;;);\n");
+        assert_eq!(
+            asi("a; [1].map(f); (function() {})();"),
+            "a\n;[1].map(f)\n;(function() { })()\n"
+        );
+        assert_eq!(asi("a; `t`.length;"), "a\n;`t`.length\n");
+        assert_eq!(asi("f(); ({a} = b);"), "f()\n;({a} = b)\n");
+        assert_eq!(asi("f(); [a] = b;"), "f()\n;([a] = b)\n");
+        assert_eq!(asi("if (a) [1].map(f);"), "if (a) [1].map(f)\n\n");
+        assert_eq!(
+            asi("if (a) b; else [1].map(f);"),
+            "if (a) b\n else [1].map(f)\n\n"
+        );
+        assert_eq!(
+            asi("while (a) (function() {})();"),
+            "while (a) (function() { })()\n"
+        );
+        assert_eq!(asi("do [x]; while (c);"), "do [x]\n while (c)\n");
+        assert_eq!(
+            asi("{ a; [1].map(f); }"),
+            "{\n    a\n\n    ;[1].map(f)\n}\n"
+        );
+        let w = Writer::builder()
+            .minify(true)
+            .semicolons(SemicolonPolicy::Never)
+            .build(Vec::new());
+        let s = write_out_with(w, |w| {
+            for part in ressa::Parser::new("let a = 1; a++; [a].map(f);").unwrap() {
+                w.write_part(&part.unwrap())?;
+            }
+            Ok(())
+        });
+        assert_eq!(s, "let a = 1;a++;[a].map(f);");
+    }
+    #[test]
+    fn write_for_await_of() {
+        // RESSA reads a top level `async function` as an expression
+        // statement, so the loop is put in a function expression instead
+        assert_eq!(
+            round_trip("const f = async function() { for await (const x of gen()) {} };"),
+            "const f = async function() {\n    for await (const x of gen()) {\n        \n    }\n};\n\n"
+        );
+    }
+    #[test]
+    fn write_import_meta() {
+        let meta = Expr::MetaProperty(MetaProperty {
+            meta: "import".to_string(),
+            property: "meta".to_string(),
+        });
+        let s = write_out(|w| w.write_expr(&meta));
+        assert_eq!(s, "import.meta");
+        let url = Expr::member(meta, Expr::ident("url"), false);
+        let s = write_out(|w| w.write_expr(&url));
+        assert_eq!(s, "import.meta.url");
+    }
+    #[test]
+    fn logical_precedence_wrapping() {
+        assert_eq!(round_trip("a || b || c;"), "a || b || c;\n\n");
+        assert_eq!(round_trip("a && b || c;"), "a && b || c;\n\n");
+        assert_eq!(round_trip("a || b && c;"), "a || b && c;\n\n");
+        assert_eq!(round_trip("(a || b) && c;"), "(a || b) && c;\n\n");
+        assert_eq!(round_trip("a && (b || c);"), "a && (b || c);\n\n");
+        assert_eq!(round_trip("a || (b || c);"), "a || (b || c);\n\n");
+    }
+    #[test]
+    fn brace_style_next_line() {
+        let next_line = |js: &str| {
+            let w = Writer::builder()
+                .brace_style(BraceStyle::NextLine)
+                .build(Vec::new());
+            write_out_with(w, |w| {
+                for part in ressa::Parser::new(js).unwrap() {
+                    w.write_part(&part.unwrap())?;
+                }
+                Ok(())
+            })
+        };
+        assert_eq!(
+            next_line("function f() { if (a) { b(); } }"),
+            "function f()\n{\n    if (a)\n    {\n        b();\n    }\n}\n\n"
+        );
+        assert_eq!(
+            next_line("try {} catch (e) {}"),
+            "try\n{\n    \n} catch (e)\n{\n    \n}\n\n"
+        );
+        assert_eq!(next_line("let {a} = {a: 1};"), "let {a} = {a: 1};\n\n");
+    }
+    #[test]
+    fn write_static_members() {
+        let method = Property::new(
+            PropertyKey::Expr(Expr::ident("create")),
+            PropertyValue::Expr(Expr::Function(Function::new(
+                None,
+                vec![],
+                vec![],
+                false,
+                false,
+            ))),
+            PropertyKind::Method,
+            true,
+            false,
+            false,
+        );
+        let s = write_out(|w| w.write_static_property(&method));
+        assert_eq!(s, "static create(){ }");
+        let s = write_out(|w| w.write_static_block(&[]));
+        assert_eq!(s, "static {\n    \n}");
+        let s = write_out(|w| w.write_static_block(&[ProgramPart::Stmt(Stmt::Debugger)]));
+        assert_eq!(s, "static {\n    debugger;\n}");
+    }
+    #[test]
+    fn write_hashbang() {
+        let parts = vec![ProgramPart::Stmt(Stmt::Debugger)];
+        let w = Writer::builder()
+            .hashbang(Some("/usr/bin/env node"))
+            .build(Vec::new());
+        let s = write_out_with(w, |w| w.write_program(&Program::Script(parts.clone())));
+        assert_eq!(s, "#!/usr/bin/env node\ndebugger;\n\n");
+        let w = Writer::builder()
+            .hashbang(Some("#!/usr/bin/env node"))
+            .build(Vec::new());
+        let s = write_out_with(w, |w| w.write_program(&Program::Script(parts.clone())));
+        assert_eq!(s, "#!/usr/bin/env node\ndebugger;\n\n");
+        let w = Writer::builder()
+            .hashbang(Some("/usr/bin/env node"))
+            .build(Vec::new());
+        let s = write_out_with(w, |w| w.write_program(&Program::Mod(parts)));
+        assert_eq!(s, "debugger;\n\n");
+    }
+    #[test]
+    fn write_comment_placement() {
+        let comment = || Comment {
+            kind: CommentKind::Single,
+            content: " note".to_string(),
+            tail_content: None,
+        };
+        let s = write_out(|w| {
+            w.write_open_brace()?;
+            w.write_new_line()?;
+            w.write_comment_before(comment())?;
+            w.write_leading_whitespace()?;
+            w.write_stmt(&Stmt::Debugger)
+        });
+        assert_eq!(s, "{\n    // note\n    debugger;\n");
+        let s = write_out(|w| {
+            w.write_expr(&Expr::ident("a"))?;
+            w.write_empty_stmt()?;
+            w.write_comment_after(comment())
+        });
+        assert_eq!(s, "a; // note");
+        let w = Writer::builder().minify(true).build(Vec::new());
+        let s = write_out_with(w, |w| {
+            w.write_comment_before(comment())?;
+            w.write_stmt(&Stmt::Debugger)
+        });
+        assert_eq!(s, "// note\ndebugger;");
+    }
+    #[test]
+    fn set_indent_level_below_open_braces() {
+        let mut w = Writer::new(Vec::new());
+        w.write_open_brace().unwrap();
+        w.set_indent_level(0);
+        w.write_close_brace().unwrap();
+        assert_eq!(w.indent_level(), 0);
+        assert_eq!(w.into_string().unwrap(), "{}");
+    }
+    #[test]
+    fn write_conditional_test_wrapping() {
+        assert_eq!(
+            round_trip("(a ? b : c) ? d : e;"),
+            "(a ? b : c) ? d : e;\n\n"
+        );
+        assert_eq!(round_trip("(a = b) ? c : d;"), "(a = b) ? c : d;\n\n");
+        assert_eq!(round_trip("a ? b : c ? d : e;"), "a ? b : c ? d : e;\n\n");
+    }
+    #[test]
+    fn short_writes() {
+        /// Only accepts a single byte for each call to `write`
+        struct OneByte(Vec<u8>);
+        impl Write for OneByte {
+            fn write(&mut self, buf: &[u8]) -> Result<usize, IoError> {
+                match buf.first() {
+                    Some(b) => {
+                        self.0.push(*b);
+                        Ok(1)
+                    }
+                    None => Ok(0),
+                }
+            }
+            fn flush(&mut self) -> Result<(), IoError> {
+                Ok(())
+            }
+        }
+        let mut w = Writer::new(OneByte(Vec::new()));
+        w.write_stmt(&Stmt::Debugger).unwrap();
+        assert_eq!(w.byte_offset(), "debugger;\n".len());
+        assert_eq!(w.into_inner().0, b"debugger;\n");
+    }
 }
@@ -21,6 +21,8 @@ pub fn re_write(s: &str, new_quote: char) -> Result<String, String> {
         }
         if let Some(next) = queue.pop_front() {
             match next {
+                // the old quote no longer needs to be escaped
+                c if c == old_quote => s.push(old_quote),
                 'b' =>  s.push_str(r#"\u{0008}"#),
                 'f' =>  s.push_str(r#"\u{000C}"#),
                 'n' =>  s.push_str(r#"\n"#),
@@ -32,7 +34,6 @@ pub fn re_write(s: &str, new_quote: char) -> Result<String, String> {
                 'u' =>  s.push_str(&unescape_unicode(&mut queue)),
                 'x' =>  s.push_str(&unescape_hex(&mut queue)),
                 'o' =>  s.push_str(&unescape_octal(&mut queue)),
-                c if c == old_quote => s.push(old_quote),
                 c => s.push_str(&format!("\\{}", c)),
             }
         } else {
@@ -110,4 +111,24 @@ mod test {
         let r = re_write(one, '\'').unwrap();
         assert_eq!(r, r#"'\''"#);
     }
+
+    #[test]
+    fn re_write_same_quote() {
+        let single = r#"'it\'s "quoted"'"#;
+        assert_eq!(re_write(single, '\'').unwrap(), single);
+        let double = r#""it's \"quoted\"""#;
+        assert_eq!(re_write(double, '"').unwrap(), double);
+    }
+
+    #[test]
+    fn re_write_single_to_double() {
+        let r = re_write(r#"'it\'s "quoted"'"#, '"').unwrap();
+        assert_eq!(r, r#""it's \"quoted\"""#);
+    }
+
+    #[test]
+    fn re_write_double_to_single() {
+        let r = re_write(r#""he said \"hello\" it's""#, '\'').unwrap();
+        assert_eq!(r, r#"'he said "hello" it\'s'"#);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,150 @@
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// A single mapping from a position in the generated
+/// output to a position in the original source, all
+/// values are zero based
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mapping {
+    pub generated_line: usize,
+    pub generated_column: usize,
+    pub source_line: usize,
+    pub source_column: usize,
+}
+
+/// The mappings recorded by a `Writer` for a single
+/// source file, these can be serialized as a
+/// [source map v3](https://sourcemaps.info/spec.html)
+///
+/// ```
+/// use resw::source_map::SourceMap;
+/// let mut map = SourceMap::new("in.js");
+/// map.add_mapping(0, 0, 0, 0);
+/// assert_eq!(
+///     map.to_json(),
+///     r#"{"version":3,"sources":["in.js"],"names":[],"mappings":"AAAA"}"#
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    source: String,
+    mappings: Vec<Mapping>,
+}
+
+impl SourceMap {
+    pub fn new(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            mappings: vec![],
+        }
+    }
+    /// Record a mapping, these are expected to be added
+    /// in the order they were generated
+    pub fn add_mapping(
+        &mut self,
+        generated_line: usize,
+        generated_column: usize,
+        source_line: usize,
+        source_column: usize,
+    ) {
+        self.mappings.push(Mapping {
+            generated_line,
+            generated_column,
+            source_line,
+            source_column,
+        });
+    }
+    /// The mappings recorded so far
+    pub fn mappings(&self) -> &[Mapping] {
+        &self.mappings
+    }
+    /// Serialize this map as source map v3 json
+    pub fn to_json(&self) -> String {
+        let mut source = String::new();
+        for c in self.source.chars() {
+            match c {
+                '"' => source.push_str("\\\""),
+                '\\' => source.push_str("\\\\"),
+                _ => source.push(c),
+            }
+        }
+        format!(
+            r#"{{"version":3,"sources":["{}"],"names":[],"mappings":"{}"}}"#,
+            source,
+            self.encode_mappings()
+        )
+    }
+    /// Encode the mappings as base64 VLQ segments, lines are
+    /// separated by `;` and segments on the same line by `,`
+    fn encode_mappings(&self) -> String {
+        let mut ret = String::new();
+        let mut line = 0;
+        let mut prev_gen_column = 0;
+        let mut prev_source_line = 0;
+        let mut prev_source_column = 0;
+        for (i, m) in self.mappings.iter().enumerate() {
+            if m.generated_line > line {
+                for _ in line..m.generated_line {
+                    ret.push(';');
+                }
+                line = m.generated_line;
+                prev_gen_column = 0;
+            } else if i > 0 {
+                ret.push(',');
+            }
+            encode_vlq(&mut ret, m.generated_column as i64 - prev_gen_column as i64);
+            // there is only ever one source
+            encode_vlq(&mut ret, 0);
+            encode_vlq(&mut ret, m.source_line as i64 - prev_source_line as i64);
+            encode_vlq(&mut ret, m.source_column as i64 - prev_source_column as i64);
+            prev_gen_column = m.generated_column;
+            prev_source_line = m.source_line;
+            prev_source_column = m.source_column;
+        }
+        ret
+    }
+}
+
+fn encode_vlq(out: &mut String, value: i64) {
+    let mut vlq = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    };
+    loop {
+        let mut digit = vlq & 0b11111;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0b100000;
+        }
+        out.push(BASE64[digit as usize] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn vlq() {
+        let encode = |v| {
+            let mut s = String::new();
+            encode_vlq(&mut s, v);
+            s
+        };
+        assert_eq!(encode(0), "A");
+        assert_eq!(encode(1), "C");
+        assert_eq!(encode(-1), "D");
+        assert_eq!(encode(16), "gB");
+        assert_eq!(encode(-6), "N");
+    }
+    #[test]
+    fn mappings() {
+        let mut map = SourceMap::new("in.js");
+        map.add_mapping(0, 0, 0, 0);
+        map.add_mapping(0, 4, 0, 6);
+        map.add_mapping(2, 0, 2, 0);
+        assert_eq!(map.encode_mappings(), "AAAA,IAAM;;AAEN");
+    }
+}
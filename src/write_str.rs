@@ -4,10 +4,26 @@ use std::{
     },
 };
 
+/// An in memory `impl Write` for capturing
+/// the output of a `Writer`
+///
+/// ```
+/// use resw::{Writer, WriteString};
+/// let mut out = WriteString::new();
+/// {
+///     let mut w = Writer::new(out.generate_child());
+///     w.write_debugger_stmt().unwrap();
+/// }
+/// assert_eq!(out.into_string().unwrap(), "debugger");
+/// ```
+#[derive(Debug, Default, Clone)]
 pub struct WriteString {
     buf: Vec<u8>,
 }
 
+/// A handle to a `WriteString` that can be given to a
+/// `Writer` while the parent is still available to read
+/// the output from once the `Writer` is dropped
 pub struct ChildWriter<'a> {
     parent: &'a mut WriteString
 }
@@ -18,18 +34,42 @@ impl WriteString {
             buf: vec![],
         }
     }
+    /// Create a `WriteString` with room for at least `n` bytes
+    pub fn with_capacity(n: usize) -> Self {
+        Self {
+            buf: Vec::with_capacity(n),
+        }
+    }
+    /// Create a writer that appends to this `WriteString`
     pub fn generate_child(&mut self) -> ChildWriter {
         ChildWriter {
             parent: self
         }
     }
+    /// Copy the bytes written so far into a `String`
     pub fn get_string(&self) -> Result<String, ::std::string::FromUtf8Error> {
         String::from_utf8(self.buf.clone())
     }
 
+    /// Copy the bytes written so far into a `String`, replacing
+    /// any invalid utf8
     pub fn get_string_lossy(&self) -> String {
         String::from_utf8_lossy(&self.buf).to_owned().to_string()
     }
+    /// Consume this `WriteString` converting the bytes written into a `String`
+    pub fn into_string(self) -> Result<String, ::std::string::FromUtf8Error> {
+        String::from_utf8(self.buf)
+    }
+    /// Consume this `WriteString` returning the bytes written
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+impl AsRef<[u8]> for WriteString {
+    fn as_ref(&self) -> &[u8] {
+        &self.buf
+    }
 }
 
 impl Write for WriteString {
@@ -66,4 +106,15 @@ mod test {
         let s = w.get_string().unwrap();
         assert_eq!(s, "asdf".repeat(100));
     }
+    #[test]
+    fn into_string_and_bytes() {
+        let mut w = WriteString::with_capacity(8);
+        w.write_all(b"asdf").unwrap();
+        assert_eq!(w.as_ref(), b"asdf");
+        assert_eq!(w.clone().into_bytes(), b"asdf".to_vec());
+        assert_eq!(w.into_string().unwrap(), "asdf");
+        let mut w = WriteString::new();
+        w.write_all(&[0xff]).unwrap();
+        assert!(w.into_string().is_err());
+    }
 }
\ No newline at end of file
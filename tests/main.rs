@@ -170,4 +170,25 @@ fn new_member_expr_failure() {
 }";
     let (first, second) = double_round_trip(js, false);
     check_round_trips("new_member_expr_failure", &first, &second);
-}
\ No newline at end of file
+}
+#[test]
+fn template_literal_program() {
+    use resast::prelude::*;
+    let template = TemplateLiteral::new(
+        vec![
+            TemplateElement::new(false, "things ".to_string(), "`things ${".to_string()),
+            TemplateElement::new(true, " stuff".to_string(), "} stuff`".to_string()),
+        ],
+        vec![Expr::ident("y")],
+    );
+    let program = Program::Script(vec![ProgramPart::Decl(Decl::Variable(
+        VariableKind::Let,
+        vec![VariableDecl::with_value("x", Expr::Literal(Literal::Template(template)))],
+    ))]);
+    let mut out = WriteString::new();
+    Writer::new(out.generate_child()).write_program(&program).expect("failed to write program");
+    let js = out.get_string().expect("Invalid utf-8 written to write string");
+    assert_eq!(js, "let x = `things ${y} stuff`;\n\n");
+    let (first, second) = double_round_trip(&js, false);
+    check_round_trips("template_literal_program", &first, &second);
+}
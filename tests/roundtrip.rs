@@ -0,0 +1,112 @@
+use proptest::prelude::*;
+use resast::prelude::*;
+use resw::Writer;
+
+fn leaf() -> impl Strategy<Value = Expr> {
+    prop_oneof![
+        prop::sample::select(vec!["a", "b", "c", "foo", "bar"]).prop_map(|n| Expr::ident(n)),
+        (0u32..1000).prop_map(|n| Expr::number(&n.to_string())),
+    ]
+}
+
+fn binary_operator() -> impl Strategy<Value = BinaryOperator> {
+    prop::sample::select(vec![
+        BinaryOperator::Equal,
+        BinaryOperator::NotEqual,
+        BinaryOperator::StrictEqual,
+        BinaryOperator::StrictNotEqual,
+        BinaryOperator::LessThan,
+        BinaryOperator::GreaterThan,
+        BinaryOperator::LessThanEqual,
+        BinaryOperator::GreaterThanEqual,
+        BinaryOperator::LeftShift,
+        BinaryOperator::RightShift,
+        BinaryOperator::UnsignedRightShift,
+        BinaryOperator::Plus,
+        BinaryOperator::Minus,
+        BinaryOperator::Times,
+        BinaryOperator::Over,
+        BinaryOperator::Mod,
+        BinaryOperator::Or,
+        BinaryOperator::XOr,
+        BinaryOperator::And,
+        BinaryOperator::In,
+        BinaryOperator::InstanceOf,
+        BinaryOperator::PowerOf,
+    ])
+}
+
+/// Expressions built from identifiers, numbers, operators,
+/// conditionals, calls and member access
+fn expr() -> impl Strategy<Value = Expr> {
+    leaf().prop_recursive(4, 32, 3, |inner| {
+        prop_oneof![
+            (inner.clone(), binary_operator(), inner.clone())
+                .prop_map(|(l, op, r)| Expr::binary(l, op, r)),
+            (
+                inner.clone(),
+                prop::sample::select(vec![LogicalOperator::And, LogicalOperator::Or]),
+                inner.clone()
+            )
+                .prop_map(|(l, op, r)| Expr::logical(l, op, r)),
+            (
+                prop::sample::select(vec![
+                    UnaryOperator::Minus,
+                    UnaryOperator::Plus,
+                    UnaryOperator::Not
+                ]),
+                inner.clone()
+            )
+                .prop_map(|(op, arg)| Expr::Unary(UnaryExpr::new(op, true, arg))),
+            (inner.clone(), inner.clone(), inner.clone()).prop_map(
+                |(test, consequent, alternate)| {
+                    Expr::Conditional(ConditionalExpr {
+                        test: Box::new(test),
+                        consequent: Box::new(consequent),
+                        alternate: Box::new(alternate),
+                    })
+                }
+            ),
+            (
+                prop::sample::select(vec!["f", "g"]),
+                prop::collection::vec(inner.clone(), 0..3)
+            )
+                .prop_map(|(callee, args)| Expr::call(Expr::ident(callee), args)),
+            (inner, prop::sample::select(vec!["x", "y"])).prop_map(|(obj, prop)| Expr::member(
+                obj,
+                Expr::ident(prop),
+                false
+            )),
+        ]
+    })
+}
+
+fn write(expr: &Expr) -> String {
+    let mut w = Writer::new(Vec::new());
+    w.write_stmt(&Stmt::Expr(expr.clone()))
+        .expect("failed to write expression");
+    w.into_string().expect("invalid utf-8 written")
+}
+
+proptest! {
+    #[test]
+    fn expression_round_trip(expr in expr()) {
+        let js = write(&expr);
+        // RESS reads a `/` right after the first token of the source as a regex,
+        // so parse after an empty statement
+        let src = format!(";{}", js);
+        let mut parser = ressa::Parser::new(&src).expect("failed to create parser");
+        match parser.next() {
+            Some(Ok(ProgramPart::Stmt(Stmt::Empty))) => (),
+            other => panic!("{:?} did not start with an empty statement: {:?}", src, other),
+        }
+        let part = parser
+            .next()
+            .expect("no part parsed")
+            .unwrap_or_else(|e| panic!("failed to parse {:?}: {}", js, e));
+        match part {
+            ProgramPart::Stmt(Stmt::Expr(parsed)) => prop_assert_eq!(parsed, expr, "{}", js),
+            other => panic!("{:?} parsed as {:?}", js, other),
+        }
+    }
+}